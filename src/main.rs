@@ -1,20 +1,53 @@
 mod traceedb;
+use nix::unistd::Pid;
 use object::Object;
 use object::ObjectKind;
-use traceedb::dbg::TraceeDbg;
+use traceedb::dbg::{find_pid_by_name, TraceeDbg};
 
 use std::env;
 use std::fs;
+use std::process;
 
 fn main() {
     println!("TRACEEDB DEBUGGER\nType \"help\" for command list!");
 
-    let mut args = env::args().skip(1).take(2);
+    let args: Vec<String> = env::args().skip(1).take(2).collect();
     let elf_buf: Vec<u8>;
 
     let mut builder = TraceeDbg::builder();
 
-    if let Some(prog) = args.next() {
+    // Attaching, the program is the one the process is running
+    let attach_pid = match args.first().map(String::as_str) {
+        Some("-p") => {
+            let pid = args
+                .get(1)
+                .and_then(|pid| pid.parse().ok())
+                .map(Pid::from_raw)
+                .unwrap_or_else(|| exit_with_usage("Please give -p the PID of a running process"));
+            Some(pid)
+        }
+
+        Some("--attach-name") => {
+            let name = args
+                .get(1)
+                .unwrap_or_else(|| exit_with_usage("Please give --attach-name a program name"));
+            Some(find_pid_by_name(name).unwrap_or_else(|err_msg| exit_with_usage(err_msg)))
+        }
+
+        _ => None,
+    };
+
+    let prog = match attach_pid {
+        Some(pid) => {
+            builder = builder.attach(pid);
+            fs::read_link(format!("/proc/{}/exe", pid))
+                .ok()
+                .and_then(|exe| exe.into_os_string().into_string().ok())
+        }
+        None => args.first().cloned(),
+    };
+
+    if let Some(prog) = prog {
         elf_buf = fs::read(prog.as_str()).expect("Given program not found, exiting");
         let file = object::File::parse(&*elf_buf).expect("Failed to parse program as ELF, exiting");
 
@@ -24,10 +57,20 @@ fn main() {
             _ => panic!("Please provide an ELF executable of type ET_DYN or ET_EXEC!"),
         };
 
-        builder = builder.program(prog)
+        builder = builder
+            .program(prog)
             .is_position_independent(is_et_dyn)
-            .dwarf_symbols(&elf_buf.as_slice());
+            .dwarf_symbols(elf_buf.as_slice())
+            .elf_symbols(elf_buf.as_slice())
+            .unwind_info(elf_buf.as_slice());
     }
 
     builder.build().run();
 }
+
+/// Explains what went wrong with the arguments and how to start, then exits
+fn exit_with_usage(err_msg: &str) -> ! {
+    eprintln!("{}", err_msg);
+    eprintln!("Usage: traceedb <program> | -p <pid> | --attach-name <program name>");
+    process::exit(1);
+}
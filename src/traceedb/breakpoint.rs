@@ -1,7 +1,7 @@
-use nix::{errno::Errno, libc, sys::ptrace, unistd::Pid};
+use crate::traceedb::memory;
+use nix::unistd::Pid;
 
-use std::ffi::{c_uint, c_void};
-use std::ptr;
+use std::ffi::c_void;
 
 #[derive(PartialEq, Eq, Debug)]
 pub struct BrkptRecord {
@@ -10,33 +10,41 @@ pub struct BrkptRecord {
     pub original_insn: i64,
 }
 
+/// A user-level breakpoint, which may resolve to several locations (one
+/// BrkptRecord each) when a line or function has more than one copy.
+#[derive(Debug)]
+pub struct BrkptGroup {
+    pub id: usize,
+    pub spec: String,
+    pub locations: Vec<u64>,
+}
+
 impl BrkptRecord {
-    pub fn new(pid: Pid, text_addr: *mut c_void) -> Self {
-        let original_insn =
-            ptrace::read(pid, text_addr).expect("Failed to read text region for breakpoint!");
+    /// The byte the trap replaces is read through the process's memory, as
+    /// the thread the breakpoint is set from may be running in non-stop mode.
+    pub fn new(pid: Pid, text_addr: *mut c_void) -> Result<Self, &'static str> {
+        let original = memory::read_bytes(pid, text_addr as u64, 1)?;
 
-        Self {
+        Ok(Self {
             pid,
             pc_addr: text_addr,
-            original_insn,
-        }
+            original_insn: original[0] as i64,
+        })
     }
 
+    /// Only the first byte is ours to touch, the rest of the word may hold
+    /// another breakpoint's trap by now. It's written through the process's
+    /// memory rather than PTRACE_POKETEXT, as the thread the breakpoint was
+    /// set from may be running in non-stop mode.
     pub fn activate(&self) {
-        let trap = ((self.original_insn & 0xFFFFFF00) | 0xCC) as *mut c_void;
-        unsafe {
-            ptrace::write(self.pid, self.pc_addr, trap).unwrap();
-        }
+        memory::write_bytes(self.pid, self.pc_addr as u64, &[0xCC])
+            .expect("Failed to write trap to text region!");
     }
 
-    pub fn recover_from_trap(&self) {
-        println!("Recovering from trap!");
-        unsafe {
-            ptrace::write(self.pid, self.pc_addr, self.original_insn as *mut c_void)
-                .expect("failed to write to .text section with PTRACE_POKEDATA");
-        }
-        let mut regs = ptrace::getregs(self.pid).expect("FATAL: Failed to send PTRACE_GETREGS");
-        regs.rip -= 1;
-        ptrace::setregs(self.pid, regs).expect("FATAL: Failed to send message PTRACE_SETREGS");
+    /// Puts the original byte back, for traps that are being removed rather
+    /// than stepped past.
+    pub fn deactivate(&self) {
+        memory::write_bytes(self.pid, self.pc_addr as u64, &[self.original_insn as u8])
+            .expect("Failed to restore text region!");
     }
 }
@@ -0,0 +1,98 @@
+use crate::traceedb::syscall::syscall_name;
+use nix::sys::signal::Signal;
+
+/// Where exceptions are thrown: libstdc++'s throw, and Rust's panics as they
+/// start unwinding
+pub const THROW_FUNCTIONS: &[&str] = &["__cxa_throw", "rust_panic"];
+
+/// Where they're caught: a C++ catch block being entered, or catch_unwind
+/// taking hold of a panic
+pub const CATCH_FUNCTIONS: &[&str] = &["__cxa_begin_catch", "__rust_panic_cleanup"];
+
+/// What a catchpoint stops on
+#[derive(Debug)]
+pub enum CatchKind {
+    /// Entry to and return from these syscalls, or any syscall if empty
+    Syscall(Vec<u64>),
+    /// The process forking or vforking
+    Fork,
+    /// The process exec'ing a new program
+    Exec,
+    /// The process getting these signals, or any besides SIGTRAP and SIGINT
+    /// if empty
+    Signal(Vec<Signal>),
+    /// Exceptions being thrown or caught, trapped at these locations
+    Exception { thrown: bool, locations: Vec<u64> },
+}
+
+/// A breakpoint on an event rather than an address, numbered along with
+/// the breakpoints
+#[derive(Debug)]
+pub struct Catchpoint {
+    pub id: usize,
+    pub kind: CatchKind,
+}
+
+impl Catchpoint {
+    /// What it catches, as the breakpoint list shows it
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            CatchKind::Syscall(nrs) if nrs.is_empty() => String::from("any syscall"),
+
+            CatchKind::Syscall(nrs) => {
+                let names: Vec<String> = nrs
+                    .iter()
+                    .map(|nr| format!("'{}' [{}]", syscall_name(*nr).unwrap_or("?"), nr))
+                    .collect();
+
+                format!(
+                    "syscall{} {}",
+                    if nrs.len() > 1 { "s" } else { "" },
+                    names.join(" ")
+                )
+            }
+
+            CatchKind::Fork => String::from("fork"),
+            CatchKind::Exec => String::from("exec"),
+
+            CatchKind::Signal(signals) if signals.is_empty() => {
+                String::from("signal <standard signals>")
+            }
+            CatchKind::Signal(signals) if signals.len() == Signal::iterator().count() => {
+                String::from("signal <any signal>")
+            }
+            CatchKind::Signal(signals) => {
+                let names: Vec<&str> = signals.iter().map(|signal| signal.as_str()).collect();
+                format!("signal {}", names.join(" "))
+            }
+
+            CatchKind::Exception { thrown: true, .. } => String::from("exception throw"),
+            CatchKind::Exception { thrown: false, .. } => String::from("exception catch"),
+        }
+    }
+
+    pub fn catches_syscall(&self, nr: u64) -> bool {
+        match &self.kind {
+            CatchKind::Syscall(nrs) => nrs.is_empty() || nrs.contains(&nr),
+            _ => false,
+        }
+    }
+
+    pub fn catches_signal(&self, signal: Signal) -> bool {
+        match &self.kind {
+            CatchKind::Signal(signals) if signals.is_empty() => {
+                !matches!(signal, Signal::SIGTRAP | Signal::SIGINT)
+            }
+            CatchKind::Signal(signals) => signals.contains(&signal),
+            _ => false,
+        }
+    }
+
+    /// Whether one of its traps is the breakpoint at `addr`
+    pub fn traps_at(&self, addr: u64) -> bool {
+        match &self.kind {
+            CatchKind::Exception { locations, .. } => locations.contains(&addr),
+            _ => false,
+        }
+    }
+}
@@ -1,28 +1,47 @@
 use crate::traceedb::breakpoint::BrkptRecord;
-use nix::{sys::ptrace, unistd::Pid};
-use procmaps::Mappings;
+use crate::traceedb::catchpoint::CatchKind;
+use crate::traceedb::dbg::TraceeDbg;
+use crate::traceedb::disasm::*;
+use crate::traceedb::memory::MemRegions;
+use crate::traceedb::register::*;
+use crate::traceedb::symbol::{
+    addr_to_src_line, addr_to_symbol, function_scope_at, load_elf_sections, src_lines_in,
+    symbol_name_to_addrs, ElfSection, ElfSymbol,
+};
+use crate::traceedb::thread::ThreadState;
+use nix::{
+    sys::{
+        ptrace,
+        signal::{self, Signal},
+    },
+    unistd::Pid,
+};
+use procmaps::{Map, Mappings, Path, Permissions, Privacy};
 
+use std::collections::HashMap;
 use std::ffi::c_void;
+use std::fs;
 
 pub enum TargetStat {
     AwaitingCommand,
     Running,
     Killed,
-    BreakpointAdded(BrkptRecord),
+    Detached,
+    BreakpointAdded(String, Vec<BrkptRecord>),
 }
 
 pub trait Execute {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str>;
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str>;
 }
 
 pub trait Help {
-    fn help() -> ();
+    fn help();
 }
 
 macro_rules! define_help {
     ($implementee:ident, $help_msg:literal) => {
         impl Help for $implementee {
-            fn help() -> () {
+            fn help() {
                 println!($help_msg)
             }
         }
@@ -33,55 +52,255 @@ macro_rules! define_help {
 pub struct Step;
 
 impl Execute for Step {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
-        ptrace::step(pid, None)
-            .map(|_| TargetStat::Running)
-            .map_err(|err_no| {
-                eprintln!("ERRNO {}", err_no);
-                "failed to PTRACE_SINGLESTEP"
-            })
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        // Entering an inlined call we're stopped at the head of doesn't move the pc
+        if let Some(name) = dbg.step_into_inline(pid) {
+            println!("Stepped into inlined call to {}", name);
+            return Ok(TargetStat::AwaitingCommand);
+        }
+
+        dbg.resume_thread(pid, true).map(|_| TargetStat::Running)
     }
 }
 
 define_help!(Step, "s/step = step through process");
 
 #[derive(Debug)]
-pub struct Continue;
+pub struct StepInstruction {
+    pub count: usize,
+    pub over_calls: bool,
+}
+
+impl Execute for StepInstruction {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        dbg.set_pending_steps(self.count.saturating_sub(1), self.over_calls);
+        dbg.step_instruction(pid, self.over_calls)
+    }
+}
+
+define_help!(
+    StepInstruction,
+    "si/stepi [count], ni/nexti [count] = step by instructions, nexti stepping over calls"
+);
+
+#[derive(Debug)]
+pub struct Continue {
+    pub all: bool,
+}
 
 impl Execute for Continue {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
-        ptrace::cont(pid, None)
-            .map(|_| TargetStat::Running)
-            .map_err(|err_no| {
-                eprintln!("ERRNO {}", err_no);
-                "failed to PTRACE_CONT"
-            })
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        // Non-stop mode resumes just the selected thread unless told otherwise
+        if dbg.non_stop() && !self.all {
+            dbg.resume_thread(pid, false)
+        } else {
+            dbg.continue_all(pid)
+        }
+        .map(|_| TargetStat::Running)
+    }
+}
+
+define_help!(
+    Continue,
+    "c/continue [-a] = run through process, in non-stop mode only the selected thread unless -a"
+);
+
+#[derive(Debug)]
+pub struct Interrupt {
+    pub all: bool,
+}
+
+impl Execute for Interrupt {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let running: Vec<Pid> = dbg
+            .threads()
+            .iter()
+            .filter(|thread| thread.state != ThreadState::Stopped)
+            .filter(|thread| self.all || thread.tid == pid)
+            .map(|thread| thread.tid)
+            .collect();
+
+        if running.is_empty() {
+            return Err("Nothing is running to interrupt!");
+        }
+
+        dbg.halt_threads(&running);
+
+        for thread in dbg
+            .threads()
+            .iter()
+            .filter(|thread| running.contains(&thread.tid))
+        {
+            println!("[Thread {} (LWP {}) stopped]", thread.id, thread.tid);
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    Interrupt,
+    "interrupt [-a] = in non-stop mode, stop the selected thread, or every thread with -a"
+);
+
+#[derive(Debug)]
+pub struct SetNonStop {
+    pub enabled: bool,
+}
+
+impl Execute for SetNonStop {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        dbg.set_non_stop(self.enabled);
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    SetNonStop,
+    "set non-stop <on | off> = whether a stop halts only the thread it happens in, or the whole process"
+);
+
+#[derive(Debug)]
+pub struct SetFollowForkMode {
+    pub child: bool,
+}
+
+impl Execute for SetFollowForkMode {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        dbg.set_follow_fork_child(self.child);
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    SetFollowForkMode,
+    "set follow-fork-mode <parent | child> = which process to go on debugging after a fork"
+);
+
+#[derive(Debug)]
+pub struct SetDetachOnFork {
+    pub enabled: bool,
+}
+
+impl Execute for SetDetachOnFork {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        dbg.set_detach_on_fork(self.enabled);
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    SetDetachOnFork,
+    "set detach-on-fork <on | off> = let go of the process not followed after a fork, or keep it stopped"
+);
+
+#[derive(Debug)]
+pub struct SendSignal {
+    pub signal: Option<Signal>,
+}
+
+impl Execute for SendSignal {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        dbg.set_pending_signal(self.signal);
+        Continue { all: false }.execute(pid, dbg)
+    }
+}
+
+define_help!(
+    SendSignal,
+    "signal <SIG | 0> = continue, delivering a signal (or none) to the process"
+);
+
+#[derive(Debug)]
+pub struct HandleSignal {
+    pub signals: Vec<Signal>,
+    pub actions: Vec<String>,
+}
+
+impl Execute for HandleSignal {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        for signal in &self.signals {
+            let mut policy = dbg.signal_policy(*signal);
+
+            for action in &self.actions {
+                policy.apply(action)?;
+            }
+            dbg.set_signal_policy(*signal, policy);
+        }
+
+        print_signal_table(dbg, &self.signals);
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    HandleSignal,
+    "handle <SIG | all> [no]stop [no]print [no]pass = choose what happens when the process gets a signal"
+);
+
+#[derive(Debug)]
+pub struct InfoSignals {
+    pub signal: Option<Signal>,
+}
+
+impl Execute for InfoSignals {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let signals: Vec<Signal> = match self.signal {
+            Some(signal) => vec![signal],
+            None => Signal::iterator().collect(),
+        };
+
+        print_signal_table(dbg, &signals);
+        Ok(TargetStat::AwaitingCommand)
     }
 }
 
-define_help!(Continue, "c/continue = run through process");
+define_help!(
+    InfoSignals,
+    "i/info signals [SIG] = show what happens when the process gets each signal"
+);
+
+fn print_signal_table(dbg: &TraceeDbg, signals: &[Signal]) {
+    let yes_no = |pred: bool| if pred { "Yes" } else { "No" };
+
+    println!("{:<12}{:<8}{:<8}Pass", "Signal", "Stop", "Print");
+    for signal in signals {
+        let policy = dbg.signal_policy(*signal);
+        println!(
+            "{:<12}{:<8}{:<8}{}",
+            signal.as_str(),
+            yes_no(policy.stop),
+            yes_no(policy.print),
+            yes_no(policy.pass)
+        );
+    }
+}
 
 #[derive(Debug)]
-pub struct ViewRegisters;
+pub struct ViewRegisters {
+    pub names: Vec<String>,
+}
 
 impl Execute for ViewRegisters {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
+    fn execute(&self, pid: Pid, _dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
         match ptrace::getregs(pid) {
             Ok(regs) => {
-                println!(
-                    "%RIP: {:#0x}\n\
-                    %RAX: {:#0x}\n%RBX: {:#0x}\n%RCX: {:#0x}\n%RDX: {:#0x}\n\
-                    %RBP: {:#0x}\n%RSP: {:#0x}\n%RSI: {:#0x}\n%RDI: {:#0x}",
-                    regs.rip,
-                    regs.rax,
-                    regs.rbx,
-                    regs.rcx,
-                    regs.rdx,
-                    regs.rbp,
-                    regs.rsp,
-                    regs.rsi,
-                    regs.rdi
-                );
+                let names: Vec<&str> = if self.names.is_empty() {
+                    GP_REGISTERS.to_vec()
+                } else {
+                    self.names.iter().map(String::as_str).collect()
+                };
+
+                for name in names {
+                    let val = reg_value(&regs, name).ok_or("Unknown register name!")?;
+
+                    if name == "eflags" {
+                        println!("%EFLAGS: {:#0x} {}", val, decode_eflags(val));
+                    } else {
+                        println!("%{}: {:#0x}", name.to_ascii_uppercase(), val);
+                    }
+                }
+
                 Ok(TargetStat::AwaitingCommand)
             }
 
@@ -93,13 +312,272 @@ impl Execute for ViewRegisters {
     }
 }
 
-define_help!(ViewRegisters, "reg/registers = view register contents");
+define_help!(
+    ViewRegisters,
+    "reg/registers, i/info registers [name...] = view register contents"
+);
+
+#[derive(Debug)]
+pub struct SetRegister {
+    pub name: String,
+    pub val: u64,
+}
+
+impl Execute for SetRegister {
+    fn execute(&self, pid: Pid, _dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let mut regs = ptrace::getregs(pid).map_err(|err_no| {
+            eprintln!("ERRNO {}", err_no);
+            "failed to PTRACE_GETREGS"
+        })?;
+
+        *reg_mut(&mut regs, &self.name).ok_or("Unknown register name!")? = self.val;
+
+        ptrace::setregs(pid, regs)
+            .map(|_| TargetStat::AwaitingCommand)
+            .map_err(|err_no| {
+                eprintln!("ERRNO {}", err_no);
+                "failed to PTRACE_SETREGS"
+            })
+    }
+}
+
+define_help!(
+    SetRegister,
+    "set $<register> = <value> = write a value into a register"
+);
+
+#[derive(Debug)]
+pub struct InfoFloat;
+
+impl Execute for InfoFloat {
+    fn execute(&self, pid: Pid, _dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let fp_state = FpState::read(pid)?;
+
+        for idx in 0..8 {
+            let st = fp_state.st(idx);
+            println!(
+                "%ST{}: {} {}",
+                idx,
+                format_raw(&st),
+                format_float(f80_to_f64(st))
+            );
+        }
+
+        println!(
+            "%FCTRL: {:#0x}\n%FSTAT: {:#0x}\n%FTAG: {:#0x}\n%FOP: {:#0x}\n%MXCSR: {:#0x}",
+            fp_state.fcw(),
+            fp_state.fsw(),
+            fp_state.ftw(),
+            fp_state.fop(),
+            fp_state.mxcsr()
+        );
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(InfoFloat, "i/info float = view the x87 FPU registers");
+
+#[derive(Debug)]
+pub struct InfoVector {
+    pub regs: Vec<(VectorKind, usize)>,
+}
+
+impl Execute for InfoVector {
+    fn execute(&self, pid: Pid, _dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let fp_state = FpState::read(pid)?;
+
+        // Show the widest registers the tracee actually has by default
+        let regs = if self.regs.is_empty() {
+            if fp_state.has_avx512() {
+                (0..32).map(|idx| (VectorKind::Zmm, idx)).collect()
+            } else if fp_state.has_avx() {
+                (0..16).map(|idx| (VectorKind::Ymm, idx)).collect()
+            } else {
+                (0..16).map(|idx| (VectorKind::Xmm, idx)).collect()
+            }
+        } else {
+            self.regs.clone()
+        };
+
+        for (kind, idx) in regs {
+            let bytes = fp_state
+                .vector(kind, idx)
+                .ok_or("Register not available on this machine!")?;
+
+            println!(
+                "%{}{}: {}",
+                format!("{:?}", kind).to_uppercase(),
+                idx,
+                format_raw(&bytes)
+            );
+            for (label, view) in [
+                ("f32", LaneView::F32),
+                ("f64", LaneView::F64),
+                ("i32", LaneView::I32),
+                ("i64", LaneView::I64),
+            ] {
+                println!("  {} = {}", label, format_lanes(&bytes, view));
+            }
+        }
+
+        println!("%MXCSR: {:#0x}", fp_state.mxcsr());
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    InfoVector,
+    "i/info vector [xmmN|ymmN|zmmN...] = view SSE/AVX registers as floats, doubles and ints"
+);
+
+#[derive(Debug)]
+pub struct SetFpRegister {
+    pub target: FpTarget,
+    pub bytes: Vec<u8>,
+}
+
+impl Execute for SetFpRegister {
+    fn execute(&self, pid: Pid, _dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let mut fp_state = FpState::read(pid)?;
+
+        match self.target {
+            FpTarget::St(idx) => {
+                let bytes = self.bytes.as_slice().try_into();
+                fp_state.set_st(idx, bytes.map_err(|_| "Bad x87 register value!")?);
+            }
+
+            FpTarget::Vector(kind, idx) => {
+                fp_state
+                    .set_vector(kind, idx, &self.bytes)
+                    .ok_or("Register not available on this machine!")?;
+            }
+
+            FpTarget::Lane(kind, idx, view, lane) => {
+                let mut reg = fp_state
+                    .vector(kind, idx)
+                    .ok_or("Register not available on this machine!")?;
+
+                let offset = lane * view.width();
+                reg[offset..offset + view.width()].copy_from_slice(&self.bytes);
+                fp_state.set_vector(kind, idx, &reg);
+            }
+        }
+
+        fp_state.write(pid).map(|_| TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    SetFpRegister,
+    "set $stN | $xmmN|$ymmN|$zmmN[.f32|f64|i8|i16|i32|i64[lane]] = <value> = write an x87 or vector register"
+);
+
+#[derive(Debug)]
+pub struct InfoThreads;
+
+impl Execute for InfoThreads {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        println!("  {:<6}{:<16}Frame", "Id", "Target Id");
+
+        for thread in dbg.threads().iter() {
+            let frame = match ptrace::getregs(thread.tid) {
+                _ if thread.state != ThreadState::Stopped => String::from("(running)"),
+                Ok(regs) => format!(
+                    "{:#x} {}",
+                    regs.rip,
+                    location_desc(thread.tid, dbg, regs.rip)
+                )
+                .trim_end()
+                .to_string(),
+                Err(_) => String::from("(running)"),
+            };
+
+            // Main threads stand for their process, which matters once
+            // there's more than one after a fork
+            let target_id = if thread.tid == thread.pid {
+                format!("process {}", thread.pid)
+            } else {
+                format!("LWP {}", thread.tid)
+            };
+
+            println!(
+                "{} {:<6}{:<16}{}",
+                if thread.tid == pid { '*' } else { ' ' },
+                thread.id,
+                target_id,
+                frame
+            );
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    InfoThreads,
+    "i/info threads = list the process's threads, * marking the selected one"
+);
+
+#[derive(Debug)]
+pub struct SelectThread {
+    pub id: Option<usize>,
+}
+
+impl Execute for SelectThread {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let Some(id) = self.id else {
+            let thread = dbg
+                .threads()
+                .iter()
+                .find(|thread| thread.tid == pid)
+                .map(|thread| thread.id);
+            println!(
+                "[Current thread is {} (LWP {})]",
+                thread.unwrap_or_default(),
+                pid
+            );
+            return Ok(TargetStat::AwaitingCommand);
+        };
+
+        let tid = dbg.select_thread(id)?;
+        println!("[Switching to thread {} (LWP {})]", id, tid);
+
+        let running = dbg
+            .threads()
+            .iter()
+            .any(|thread| thread.tid == tid && thread.state != ThreadState::Stopped);
+        if running {
+            println!("(running)");
+            return Ok(TargetStat::AwaitingCommand);
+        }
+
+        ExamineInsns {
+            addr: AddrSpec::Register(String::from("rip")),
+            count: 1,
+        }
+        .execute(tid, dbg)
+    }
+}
+
+define_help!(
+    SelectThread,
+    "thread [N] = show the selected thread, or select thread N for register and step commands"
+);
 
 #[derive(Debug)]
 pub struct Quit;
 
 impl Execute for Quit {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        // Processes kept after a fork go too
+        for other in dbg.processes() {
+            if other != dbg.process_of(pid) {
+                let _ = signal::kill(other, Signal::SIGKILL);
+            }
+        }
+
         ptrace::kill(pid)
             .map(|_| TargetStat::Killed)
             .map_err(|err_no| {
@@ -111,18 +589,75 @@ impl Execute for Quit {
 
 define_help!(Quit, "q/quit = quit debugger and kill process");
 
+#[derive(Debug)]
+pub struct Detach;
+
+impl Execute for Detach {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        for process in dbg.processes() {
+            dbg.detach_process(process, false);
+            println!("Detached from process {}", process);
+        }
+
+        Ok(TargetStat::Detached)
+    }
+}
+
+define_help!(
+    Detach,
+    "detach = take out breakpoints and let the process go on running untraced, quitting the debugger"
+);
+
 #[derive(Debug)]
 pub struct HelpMe;
 
 impl Execute for HelpMe {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
+    fn execute(&self, _pid: Pid, _dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
         println!("List of Commands:");
         Step::help();
+        StepInstruction::help();
         Continue::help();
+        Interrupt::help();
+        SetNonStop::help();
+        SetFollowForkMode::help();
+        SetDetachOnFork::help();
+        SendSignal::help();
+        HandleSignal::help();
+        InfoSignals::help();
         ViewRegisters::help();
+        SetRegister::help();
+        InfoFloat::help();
+        InfoVector::help();
+        SetFpRegister::help();
         ReadWord::help();
         WriteWord::help();
+        WriteMemory::help();
+        WriteBytes::help();
+        WriteString::help();
         Breakpoint::help();
+        CatchSyscall::help();
+        CatchFork::help();
+        CatchExec::help();
+        CatchSignal::help();
+        CatchException::help();
+        TraceSyscalls::help();
+        InfoBreakpoints::help();
+        InfoSymbol::help();
+        InfoProcMappings::help();
+        FindMemory::help();
+        DumpMemory::help();
+        RestoreMemory::help();
+        SaveSnapshot::help();
+        DiffSnapshot::help();
+        DeleteSnapshot::help();
+        ListSnapshots::help();
+        ExamineMemory::help();
+        ExamineInsns::help();
+        Disassemble::help();
+        Backtrace::help();
+        InfoThreads::help();
+        SelectThread::help();
+        Detach::help();
         Quit::help();
         HelpMe::help();
 
@@ -138,16 +673,12 @@ pub struct ReadWord {
 }
 
 impl Execute for ReadWord {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
-        ptrace::read(pid, self.addr)
-            .map(|val| {
-                println!("@ {:#0x}: {:#0x}", self.addr as usize, val);
-                TargetStat::AwaitingCommand
-            })
-            .map_err(|err_no| {
-                eprintln!("ERRNO {}", err_no);
-                "failed to PTRACE_CONT"
-            })
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let bytes = dbg.read_memory(pid, self.addr as u64, 8)?;
+        let val = i64::from_le_bytes(bytes.try_into().unwrap());
+
+        println!("@ {:#0x}: {:#0x}", self.addr as usize, val);
+        Ok(TargetStat::AwaitingCommand)
     }
 }
 
@@ -163,15 +694,11 @@ pub struct WriteWord {
 }
 
 impl Execute for WriteWord {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
-        unsafe {
-            ptrace::write(pid, self.addr, self.val)
-                .map(|_| TargetStat::AwaitingCommand)
-                .map_err(|err_no| {
-                    eprintln!("ERRNO {}", err_no);
-                    "failed to PTRACE_CONT"
-                })
-        }
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let val = self.val as u64;
+
+        dbg.write_memory(pid, self.addr as u64, &val.to_le_bytes())
+            .map(|_| TargetStat::AwaitingCommand)
     }
 }
 
@@ -180,32 +707,1310 @@ define_help!(
     "w/write <hex address> <hex value> = write word to address in process space"
 );
 
-pub struct Breakpoint(pub u64);
+#[derive(Debug)]
+pub struct WriteMemory {
+    pub addr: AddrSpec,
+    pub bytes: Vec<u8>,
+}
 
-impl Execute for Breakpoint {
-    fn execute(&self, pid: Pid, is_et_dyn: bool) -> Result<TargetStat, &'static str> {
-        let mut text_addr = self.0;
+impl Execute for WriteMemory {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let addr = self.addr.resolve(pid)?;
 
-        if is_et_dyn {
-            text_addr += get_segment_base_addr(pid)? as u64
-        }
+        dbg.write_memory(pid, addr, &self.bytes)
+            .map(|_| TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    WriteMemory,
+    "set {{<type>}} <hex address | $register> = <value> = write a sized value to memory"
+);
 
-        let brkptrec = BrkptRecord::new(pid, text_addr as *mut c_void);
+#[derive(Debug)]
+pub struct WriteBytes {
+    pub addr: AddrSpec,
+    pub bytes: Vec<u8>,
+}
 
-        Ok(TargetStat::BreakpointAdded(brkptrec))
+impl Execute for WriteBytes {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let addr = self.addr.resolve(pid)?;
+
+        dbg.write_memory(pid, addr, &self.bytes)
+            .map(|_| TargetStat::AwaitingCommand)
     }
 }
 
 define_help!(
-    Breakpoint,
-    "b/breakpoint <file:line> = a standard breakpoint"
+    WriteBytes,
+    "write-bytes <hex address | $register> <hex byte...> = write raw bytes to memory"
 );
 
-fn get_segment_base_addr(pid: Pid) -> Result<usize, &'static str> {
-    Mappings::from_pid(pid.into())
-        .map_err(|_| "Failed to find segment base")?
-        .iter()
-        .nth(0)
+#[derive(Debug)]
+pub struct WriteString {
+    pub addr: AddrSpec,
+    pub text: Vec<u8>,
+}
+
+impl Execute for WriteString {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let addr = self.addr.resolve(pid)?;
+
+        let mut bytes = self.text.clone();
+        bytes.push(0);
+
+        dbg.write_memory(pid, addr, &bytes)
+            .map(|_| TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    WriteString,
+    "write-string <hex address | $register> \"<text>\" = write a NUL terminated string to memory"
+);
+
+pub struct Breakpoint {
+    pub spec: String,
+    pub addrs: Vec<u64>,
+}
+
+impl Execute for Breakpoint {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let load_bias = dbg.load_bias(pid)?;
+
+        let brkptrecs = self
+            .addrs
+            .iter()
+            .map(|addr| BrkptRecord::new(pid, (addr + load_bias) as *mut c_void))
+            .collect::<Result<_, _>>()?;
+
+        Ok(TargetStat::BreakpointAdded(self.spec.clone(), brkptrecs))
+    }
+}
+
+define_help!(
+    Breakpoint,
+    "b/breakpoint <file:line | function> = a standard breakpoint"
+);
+
+#[derive(Debug)]
+pub struct CatchSyscall {
+    pub syscalls: Vec<u64>,
+}
+
+impl Execute for CatchSyscall {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let id = dbg.add_catchpoint(CatchKind::Syscall(self.syscalls.clone()));
+        announce_catchpoint(dbg, id);
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+fn announce_catchpoint(dbg: &TraceeDbg, id: usize) {
+    if let Some(catchpoint) = dbg
+        .catchpoints()
+        .iter()
+        .find(|catchpoint| catchpoint.id == id)
+    {
+        println!("Catchpoint {} ({})", id, catchpoint.describe());
+    }
+}
+
+define_help!(
+    CatchSyscall,
+    "catch syscall [name | number...] = stop on entry to and return from a syscall, or any syscall"
+);
+
+#[derive(Debug)]
+pub struct CatchFork;
+
+impl Execute for CatchFork {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        announce_catchpoint(dbg, dbg.add_catchpoint(CatchKind::Fork));
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    CatchFork,
+    "catch fork = stop when the process forks, in whichever process is followed"
+);
+
+#[derive(Debug)]
+pub struct CatchExec;
+
+impl Execute for CatchExec {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        announce_catchpoint(dbg, dbg.add_catchpoint(CatchKind::Exec));
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    CatchExec,
+    "catch exec = stop when the process execs a new program"
+);
+
+#[derive(Debug)]
+pub struct CatchSignal {
+    pub signals: Vec<Signal>,
+}
+
+impl Execute for CatchSignal {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let id = dbg.add_catchpoint(CatchKind::Signal(self.signals.clone()));
+        announce_catchpoint(dbg, id);
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    CatchSignal,
+    "catch signal [SIG... | all] = stop when the process gets a signal, even one handled with nostop"
+);
+
+#[derive(Debug)]
+pub struct CatchException {
+    pub thrown: bool,
+}
+
+impl Execute for CatchException {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        announce_catchpoint(dbg, dbg.add_exception_catchpoint(pid, self.thrown)?);
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    CatchException,
+    "catch throw | catch = stop when a C++ exception or Rust panic is thrown, or caught"
+);
+
+#[derive(Debug)]
+pub struct TraceSyscalls {
+    pub enabled: bool,
+}
+
+impl Execute for TraceSyscalls {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        dbg.set_trace_syscalls(self.enabled);
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    TraceSyscalls,
+    "trace syscalls [on | off] = log every syscall with its arguments and result as the process runs"
+);
+
+#[derive(Debug)]
+pub struct InfoBreakpoints;
+
+impl Execute for InfoBreakpoints {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let groups = dbg.breakpoint_groups();
+        let catchpoints = dbg.catchpoints();
+
+        if groups.is_empty() && catchpoints.is_empty() {
+            println!("No breakpoints.");
+            return Ok(TargetStat::AwaitingCommand);
+        }
+
+        println!("{:<8}{:<20}What", "Num", "Address");
+
+        // Both share one numbering, listed in order
+        for id in 1..=groups.len() + catchpoints.len() {
+            if let Some(catchpoint) = catchpoints.iter().find(|catchpoint| catchpoint.id == id) {
+                println!("{:<8}{:<20}{}", id, "<CATCHPOINT>", catchpoint.describe());
+                continue;
+            }
+
+            let Some(group) = groups.iter().find(|group| group.id == id) else {
+                continue;
+            };
+
+            match group.locations.as_slice() {
+                // Didn't resolve in the program since exec'd
+                [] => println!("{:<8}{:<20}{}", group.id, "<PENDING>", group.spec),
+
+                [addr] => println!(
+                    "{:<8}{:<#20x}{} {}",
+                    group.id,
+                    addr,
+                    group.spec,
+                    location_desc(pid, dbg, *addr)
+                ),
+
+                addrs => {
+                    println!("{:<8}{:<20}{}", group.id, "<MULTIPLE>", group.spec);
+
+                    for (idx, addr) in addrs.iter().enumerate() {
+                        let loc_id = format!("{}.{}", group.id, idx + 1);
+                        println!(
+                            "{:<8}{:<#20x}{}",
+                            loc_id,
+                            addr,
+                            location_desc(pid, dbg, *addr)
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    InfoBreakpoints,
+    "i/info breakpoints = list breakpoints and their locations, and catchpoints"
+);
+
+fn location_desc(pid: Pid, dbg: &TraceeDbg, addr: u64) -> String {
+    let file_addr = addr.wrapping_sub(dbg.load_bias(pid).unwrap_or(0));
+    let src_line = dbg
+        .symbols()
+        .and_then(|dwarf| addr_to_src_line(dwarf, file_addr).ok());
+
+    match (dbg.symbolize(pid, addr), src_line) {
+        (Some(name), Some((file, line))) => format!("in {} at {}:{}", name, file, line),
+        (Some(name), None) => format!("in {}", name),
+        (None, Some((file, line))) => format!("at {}:{}", file, line),
+        (None, None) => String::new(),
+    }
+}
+
+#[derive(Debug)]
+pub struct InfoSymbol {
+    pub addr: u64,
+}
+
+impl Execute for InfoSymbol {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let symbols = dbg
+            .elf_symbols()
+            .ok_or("Cannot look up addresses without a symbol table!")?;
+
+        let file_addr = self.addr.wrapping_sub(dbg.load_bias(pid)?);
+
+        match addr_to_symbol(symbols, file_addr) {
+            Some((sym, offset)) => {
+                let section = sym.section.as_deref().unwrap_or("??");
+
+                if offset == 0 {
+                    println!("{} in section {}", sym.demangled, section);
+                } else {
+                    println!("{} + {} in section {}", sym.demangled, offset, section);
+                }
+            }
+
+            None => println!("No symbol matches {:#x}.", self.addr),
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    InfoSymbol,
+    "i/info symbol <hex address> = name the symbol containing an address"
+);
+
+#[derive(Debug)]
+pub struct Backtrace;
+
+impl Execute for Backtrace {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let unwind_info = dbg
+            .unwind_info()
+            .ok_or("Cannot walk the stack without unwind info!")?;
+
+        let load_bias = dbg.load_bias(pid)?;
+        let frames = unwind_info.backtrace(pid, load_bias)?;
+        let mut frame_no = 0;
+
+        for (idx, pc) in frames.into_iter().enumerate() {
+            // Return addresses point past the call, the call is what we want to show
+            let file_pc = pc.wrapping_sub(load_bias) - if idx > 0 { 1 } else { 0 };
+
+            let scope = dbg
+                .symbols()
+                .and_then(|dwarf| function_scope_at(dwarf, file_pc).ok())
+                .unwrap_or_default();
+
+            let mut location = dbg
+                .symbols()
+                .and_then(|dwarf| addr_to_src_line(dwarf, file_pc).ok());
+
+            // Inlined calls get a virtual frame each, innermost first, with each
+            // caller located at the call site of the function it inlined
+            let hidden = if idx == 0 { dbg.inline_skip() } else { 0 };
+
+            for (depth, call) in scope.inlined.iter().rev().enumerate() {
+                if depth >= hidden {
+                    print_frame(frame_no, None, &call.name, location.as_ref());
+                    frame_no += 1;
+                }
+
+                location = call.call_file.clone().map(|file| (file, call.call_line));
+            }
+
+            let name = scope
+                .function
+                .or_else(|| {
+                    dbg.elf_symbols()
+                        .and_then(|symbols| addr_to_symbol(symbols, file_pc))
+                        .map(|(sym, _)| sym.demangled.clone())
+                })
+                .unwrap_or_else(|| String::from("??"));
+
+            print_frame(frame_no, Some(pc), &name, location.as_ref());
+            frame_no += 1;
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(Backtrace, "bt/backtrace = print the call stack");
+
+fn print_frame(frame_no: usize, pc: Option<u64>, name: &str, location: Option<&(String, u64)>) {
+    let pc = match pc {
+        Some(pc) => format!("{:#018x} in", pc),
+        None => String::from("[inlined]"),
+    };
+
+    match location {
+        Some((file, line)) => println!("#{:<3}{} {} at {}:{}", frame_no, pc, name, file, line),
+        None => println!("#{:<3}{} {}", frame_no, pc, name),
+    }
+}
+
+/// An address given either literally or as the register holding it
+#[derive(Debug)]
+pub enum AddrSpec {
+    Addr(u64),
+    Register(String),
+}
+
+impl AddrSpec {
+    pub fn resolve(&self, pid: Pid) -> Result<u64, &'static str> {
+        match self {
+            AddrSpec::Addr(addr) => Ok(*addr),
+            AddrSpec::Register(name) => {
+                let regs = ptrace::getregs(pid).map_err(|err_no| {
+                    eprintln!("ERRNO {}", err_no);
+                    "failed to PTRACE_GETREGS"
+                })?;
+
+                reg_value(&regs, name).ok_or("Unknown register name!")
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ExamineInsns {
+    pub addr: AddrSpec,
+    pub count: usize,
+}
+
+/// Most instructions x/i will disassemble at once
+const MAX_EXAMINE_INSNS: usize = 1 << 16;
+
+impl Execute for ExamineInsns {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let len = self
+            .count
+            .checked_mul(MAX_INSN_LEN)
+            .filter(|_| self.count <= MAX_EXAMINE_INSNS)
+            .ok_or("Too many instructions to examine at once!")?;
+
+        let addr = self.addr.resolve(pid)?;
+        let code = dbg.read_code(pid, addr, len)?;
+
+        print_disassembly(pid, dbg, &disassemble(&code, addr, self.count));
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    ExamineInsns,
+    "x/<count>i <hex address | $register> = disassemble instructions at an address"
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemFormat {
+    Hex,
+    Decimal,
+    Unsigned,
+    Octal,
+    Binary,
+    Char,
+    Float,
+    Addr,
+    Str,
+}
+
+impl MemFormat {
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'x' => Some(MemFormat::Hex),
+            'd' => Some(MemFormat::Decimal),
+            'u' => Some(MemFormat::Unsigned),
+            'o' => Some(MemFormat::Octal),
+            't' => Some(MemFormat::Binary),
+            'c' => Some(MemFormat::Char),
+            'f' => Some(MemFormat::Float),
+            'a' => Some(MemFormat::Addr),
+            's' => Some(MemFormat::Str),
+            _ => None,
+        }
+    }
+}
+
+/// Longest string x/s will go looking for a terminator in
+const MAX_STRING_LEN: usize = 4096;
+
+/// Most bytes x will show at once
+const MAX_EXAMINE_LEN: usize = 1 << 20;
+
+#[derive(Debug)]
+pub struct ExamineMemory {
+    pub addr: AddrSpec,
+    pub count: usize,
+    pub format: MemFormat,
+    pub unit: usize,
+}
+
+impl Execute for ExamineMemory {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        // Strings are a unit of one byte, so this caps their count too
+        let len = self
+            .count
+            .checked_mul(self.unit)
+            .filter(|len| *len <= MAX_EXAMINE_LEN)
+            .ok_or("Too much memory to examine at once!")?;
+
+        let mut addr = self.addr.resolve(pid)?;
+
+        if self.format == MemFormat::Str {
+            for _ in 0..self.count {
+                let bytes = read_c_string(pid, dbg, addr)?;
+                println!(
+                    "{}:\t\"{}\"",
+                    dbg.describe_addr(pid, addr),
+                    bytes.escape_ascii()
+                );
+                addr += bytes.len() as u64 + 1;
+            }
+
+            return Ok(TargetStat::AwaitingCommand);
+        }
+
+        let bytes = dbg.read_memory(pid, addr, len)?;
+
+        // Hex gets a full 16 bytes a row so it lines up as a hexdump
+        let per_row = match (self.format, self.unit) {
+            (MemFormat::Hex, unit) => 16 / unit,
+            (_, 1) | (_, 2) => 8,
+            (_, unit) => 16 / unit,
+        };
+
+        for row in bytes.chunks(per_row * self.unit) {
+            let values: Vec<String> = row
+                .chunks(self.unit)
+                .map(|unit| self.format_unit(pid, dbg, unit))
+                .collect();
+
+            if self.format == MemFormat::Hex {
+                let ascii: String = row
+                    .iter()
+                    .map(|byte| match byte {
+                        0x20..=0x7e => *byte as char,
+                        _ => '.',
+                    })
+                    .collect();
+
+                let width = per_row * (self.unit * 2 + 1);
+                println!(
+                    "{:#018x}:  {:<width$} |{}|",
+                    addr,
+                    values.join(" "),
+                    ascii,
+                    width = width - 1
+                );
+            } else {
+                println!("{}:\t{}", dbg.describe_addr(pid, addr), values.join("\t"));
+            }
+
+            addr += row.len() as u64;
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+impl ExamineMemory {
+    fn format_unit(&self, pid: Pid, dbg: &TraceeDbg, unit: &[u8]) -> String {
+        let mut raw = [0u8; 8];
+        raw[..unit.len()].copy_from_slice(unit);
+        let val = u64::from_le_bytes(raw);
+
+        // Sign extend from the unit's width for decimal output
+        let shift = 64 - unit.len() * 8;
+        let signed = ((val << shift) as i64) >> shift;
+
+        match self.format {
+            MemFormat::Hex => format!("{:0width$x}", val, width = unit.len() * 2),
+            MemFormat::Decimal => signed.to_string(),
+            MemFormat::Unsigned => val.to_string(),
+            MemFormat::Octal if val == 0 => String::from("0"),
+            MemFormat::Octal => format!("0{:o}", val),
+            MemFormat::Binary => format!("{:0width$b}", val, width = unit.len() * 8),
+            MemFormat::Char => format!("{} '{}'", signed, unit[0].escape_ascii()),
+            MemFormat::Float if unit.len() == 4 => format_float(f32::from_bits(val as u32) as f64),
+            MemFormat::Float => format_float(f64::from_bits(val)),
+            MemFormat::Addr => dbg.describe_addr(pid, val),
+            MemFormat::Str => unreachable!("strings are printed whole"),
+        }
+    }
+}
+
+define_help!(
+    ExamineMemory,
+    "x[/<count><format><unit>] <hex address | $register> = examine memory, format x/d/u/o/t/c/f/a/s, unit b/h/w/g"
+);
+
+/// Reads a NUL terminated string, without the terminator
+pub fn read_c_string(pid: Pid, dbg: &TraceeDbg, addr: u64) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = Vec::new();
+
+    // Aligned chunks never straddle a page, so we can't read off the end of
+    // a mapping past the terminator
+    while bytes.len() < MAX_STRING_LEN {
+        let chunk_addr = addr + bytes.len() as u64;
+        let chunk = dbg.read_memory(pid, chunk_addr, 64 - (chunk_addr % 64) as usize)?;
+
+        match chunk.iter().position(|byte| *byte == 0) {
+            Some(nul) => {
+                bytes.extend_from_slice(&chunk[..nul]);
+                return Ok(bytes);
+            }
+            None => bytes.extend_from_slice(&chunk),
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[derive(Debug)]
+pub enum DisasRange {
+    CurrentFunction,
+    Containing(u64),
+    Function(String),
+    Addrs(u64, u64),
+}
+
+#[derive(Debug)]
+pub struct Disassemble {
+    pub range: DisasRange,
+}
+
+impl Execute for Disassemble {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let load_bias = dbg.load_bias(pid)?;
+
+        // Function ranges come from the symbol table, in file addresses
+        let function_range = |file_addr: u64| {
+            dbg.elf_symbols()
+                .and_then(|symbols| {
+                    let (sym, _) = addr_to_symbol(symbols, file_addr)?;
+                    let end = function_end(pid, symbols, sym)?;
+                    Some((sym.addr + load_bias, end + load_bias))
+                })
+                .ok_or("No function contains that address, please supply a range!")
+        };
+
+        let (start, end) = match &self.range {
+            DisasRange::CurrentFunction => {
+                let pc = AddrSpec::Register(String::from("rip")).resolve(pid)?;
+                function_range(pc.wrapping_sub(load_bias))?
+            }
+
+            DisasRange::Containing(addr) => function_range(addr.wrapping_sub(load_bias))?,
+
+            DisasRange::Function(name) => {
+                let symbols = dbg
+                    .elf_symbols()
+                    .ok_or("Cannot resolve function names without a symbol table!")?;
+
+                match symbol_name_to_addrs(symbols, name)[..] {
+                    [] => return Err("Failed to find a function by that name!"),
+                    [sym] => {
+                        let end = function_end(pid, symbols, sym).ok_or(
+                            "Failed to find where that function ends, please supply a range!",
+                        )?;
+                        (sym.addr + load_bias, end + load_bias)
+                    }
+                    _ => return Err("Please qualify the function with more of its path!"),
+                }
+            }
+
+            DisasRange::Addrs(start, end) => (*start, *end),
+        };
+
+        if end <= start {
+            return Err("Empty address range!");
+        }
+
+        let code = dbg.read_memory(pid, start, (end - start) as usize)?;
+        print_disassembly(pid, dbg, &disassemble(&code, start, usize::MAX));
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    Disassemble,
+    "disas/disassemble [function | hex address | start,end | start,+length] = disassemble a function or range"
+);
+
+/// Where a function's code ends, as a file address. Symbols such as _start
+/// carry no size, so those run up to the next symbol in their section, or
+/// else to the end of the section.
+fn function_end(pid: Pid, symbols: &[ElfSymbol], sym: &ElfSymbol) -> Option<u64> {
+    if sym.size > 0 {
+        return Some(sym.addr + sym.size);
+    }
+
+    let next_sym = symbols
+        .iter()
+        .filter(|other| other.section == sym.section && other.addr > sym.addr)
+        .map(|other| other.addr)
+        .min();
+
+    next_sym.or_else(|| {
+        let sections = fs::read(format!("/proc/{}/exe", pid))
+            .ok()
+            .and_then(|buf| load_elf_sections(&buf).ok())?;
+
+        sections
+            .iter()
+            .find(|section| section.addr <= sym.addr && sym.addr < section.addr + section.size)
+            .map(|section| section.addr + section.size)
+    })
+}
+
+/// Prints instructions gdb style, marking the current pc, naming branch
+/// targets and showing each new source line ahead of its code.
+fn print_disassembly(pid: Pid, dbg: &TraceeDbg, insns: &[DisasmInsn]) {
+    let load_bias = dbg.load_bias(pid).unwrap_or(0);
+    let pc = ptrace::getregs(pid).map(|regs| regs.rip).ok();
+
+    // The line table is walked once for the whole range
+    let line_rows = match (insns.first(), insns.last()) {
+        (Some(first), Some(last)) => dbg
+            .symbols()
+            .and_then(|dwarf| {
+                src_lines_in(
+                    dwarf,
+                    first.addr.wrapping_sub(load_bias),
+                    last.addr.wrapping_sub(load_bias) + 1,
+                )
+                .ok()
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let mut sources: HashMap<String, Option<Vec<String>>> = HashMap::new();
+    let mut prev_line: Option<(String, u64)> = None;
+
+    for insn in insns {
+        let file_addr = insn.addr.wrapping_sub(load_bias);
+        let src_line = line_rows
+            .iter()
+            .find(|row| row.start <= file_addr && file_addr < row.end)
+            .map(|row| (row.file.clone(), row.line));
+
+        if let Some((file, line)) = src_line
+            .as_ref()
+            .filter(|loc| prev_line.as_ref() != Some(loc))
+        {
+            if prev_line.as_ref().map(|(prev_file, _)| prev_file) != Some(file) {
+                println!("{}:", file);
+            }
+
+            let text = sources
+                .entry(file.clone())
+                .or_insert_with(|| {
+                    fs::read_to_string(file)
+                        .ok()
+                        .map(|src| src.lines().map(String::from).collect())
+                })
+                .as_ref()
+                .and_then(|lines| lines.get((*line as usize).wrapping_sub(1)));
+
+            println!("{}\t{}", line, text.map(String::as_str).unwrap_or(""));
+        }
+
+        if src_line.is_some() {
+            prev_line = src_line;
+        }
+
+        let marker = if Some(insn.addr) == pc { "=>" } else { "  " };
+        let location = match dbg.symbolize(pid, insn.addr) {
+            Some(name) => format!("{:#018x} <{}>", insn.addr, name),
+            None => format!("{:#018x}", insn.addr),
+        };
+
+        let target_name = insn
+            .branch_target
+            .and_then(|target| dbg.symbolize(pid, target));
+
+        match target_name {
+            Some(name) => println!("{} {}:\t{} <{}>", marker, location, insn.text, name),
+            None => println!("{} {}:\t{}", marker, location, insn.text),
+        }
+    }
+}
+
+/// Where in the tracee to look, for commands working over memory ranges
+#[derive(Debug)]
+pub enum MemRange {
+    Readable,
+    Writable,
+    Addrs(u64, u64),
+    Mapping(String),
+}
+
+impl MemRange {
+    /// Resolves to the readable address ranges covered, a mapping name may
+    /// match several (one per segment of a loaded file)
+    pub fn resolve(&self, pid: Pid) -> Result<Vec<(u64, u64)>, &'static str> {
+        if let MemRange::Addrs(start, end) = self {
+            return Ok(vec![(*start, *end)]);
+        }
+
+        let mappings =
+            Mappings::from_pid(pid.into()).map_err(|_| "Failed to read the tracee's mappings!")?;
+
+        let named: Vec<&Map> = mappings
+            .iter()
+            .filter(|map| match self {
+                MemRange::Mapping(name) => {
+                    let path = format_map_path(map);
+                    path == *name || path.ends_with(&format!("/{}", name))
+                }
+                _ => true,
+            })
+            .collect();
+
+        if named.is_empty() {
+            return Err("No mapping by that name!");
+        }
+
+        // Guard pages between a file's segments are mapped ---p, skip them
+        let ranges: Vec<(u64, u64)> = named
+            .into_iter()
+            .filter(|map| match self {
+                MemRange::Writable => map.perms.readable && map.perms.writable,
+                // The kernel's vvar pages can't be read even by a tracer
+                _ => map.perms.readable && !matches!(map.pathname, Path::Vvar),
+            })
+            .map(|map| (map.base as u64, map.ceiling as u64))
+            .collect();
+
+        if ranges.is_empty() {
+            return Err("None of that memory is readable!");
+        }
+
+        Ok(ranges)
+    }
+}
+
+/// Most matches find will list before giving up
+const MAX_FIND_RESULTS: usize = 256;
+
+/// Memory is searched this much at a time, so huge mappings aren't read
+/// into the debugger in one go
+const FIND_CHUNK_SIZE: usize = 1 << 20;
+
+#[derive(Debug)]
+pub struct FindMemory {
+    pub range: MemRange,
+    pub pattern: Vec<u8>,
+}
+
+impl Execute for FindMemory {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let mut matches = 0;
+
+        'search: for (start, end) in self.range.resolve(pid)? {
+            let mut chunk_addr = start;
+
+            while chunk_addr < end {
+                // Chunks overlap by the pattern length, so matches straddling
+                // two of them aren't missed
+                let len = (end - chunk_addr).min(FIND_CHUNK_SIZE as u64) as usize;
+                let Ok(chunk) = dbg.read_memory(pid, chunk_addr, len) else {
+                    println!("Skipped {:#x}-{:#x}, it couldn't be read", chunk_addr, end);
+                    break;
+                };
+
+                for (offset, window) in chunk.windows(self.pattern.len()).enumerate() {
+                    if window == self.pattern.as_slice() {
+                        println!("{}", dbg.describe_addr(pid, chunk_addr + offset as u64));
+                        matches += 1;
+
+                        if matches == MAX_FIND_RESULTS {
+                            println!("Stopping after {} matches", MAX_FIND_RESULTS);
+                            break 'search;
+                        }
+                    }
+                }
+
+                if chunk_addr + len as u64 >= end {
+                    break;
+                }
+                chunk_addr += (len - (self.pattern.len() - 1)) as u64;
+            }
+        }
+
+        match matches {
+            0 => println!("Pattern not found."),
+            1 => println!("1 pattern found."),
+            _ => println!("{} patterns found.", matches),
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    FindMemory,
+    "find [start,end | start,+length | mapping] <\"text\" | u\"text\" | /x <hex byte...> | /b|h|w|g <value>> = search memory for a string, bytes or integer"
+);
+
+#[derive(Debug)]
+pub struct DumpMemory {
+    pub path: String,
+    pub range: MemRange,
+}
+
+impl Execute for DumpMemory {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        // Segments of one file sit back to back, so they dump as one region
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for (start, end) in self.range.resolve(pid)? {
+            match ranges.last_mut() {
+                Some((_, prev_end)) if *prev_end == start => *prev_end = end,
+                _ => ranges.push((start, end)),
+            }
+        }
+
+        let [(start, end)] = ranges[..] else {
+            for (start, end) in &ranges {
+                println!("{:#x}-{:#x}", start, end);
+            }
+            return Err("The mapping's readable segments have gaps between them, please dump the ranges above one at a time!");
+        };
+
+        let bytes = dbg.read_memory(pid, start, (end - start) as usize)?;
+        fs::write(&self.path, &bytes).map_err(|_| "Failed to write the dump file!")?;
+
+        println!(
+            "Dumped {} bytes from {:#x}-{:#x} to {}",
+            bytes.len(),
+            start,
+            end,
+            self.path
+        );
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    DumpMemory,
+    "dump memory <file> <start end | start,end | start,+length | mapping> = save a memory region to a file"
+);
+
+#[derive(Debug)]
+pub struct RestoreMemory {
+    pub path: String,
+    pub addr: AddrSpec,
+}
+
+impl Execute for RestoreMemory {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let addr = self.addr.resolve(pid)?;
+        let bytes = fs::read(&self.path).map_err(|_| "Failed to read the dump file!")?;
+
+        dbg.write_memory(pid, addr, &bytes)?;
+
+        println!(
+            "Restored {} bytes from {} to {:#x}",
+            bytes.len(),
+            self.path,
+            addr
+        );
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    RestoreMemory,
+    "restore <file> <hex address | $register> = write a file's contents back into memory"
+);
+
+#[derive(Debug)]
+pub struct SaveSnapshot {
+    pub name: String,
+    pub range: MemRange,
+}
+
+impl Execute for SaveSnapshot {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let mut regions: MemRegions = Vec::new();
+        for (start, end) in self.range.resolve(pid)? {
+            match dbg.read_memory(pid, start, (end - start) as usize) {
+                Ok(bytes) => regions.push((start, bytes)),
+                Err(_) => println!("Skipped {:#x}-{:#x}, it couldn't be read", start, end),
+            }
+        }
+
+        let size: usize = regions.iter().map(|(_, bytes)| bytes.len()).sum();
+        println!(
+            "Saved snapshot \"{}\", {} bytes over {} regions",
+            self.name,
+            size,
+            regions.len()
+        );
+        dbg.save_snapshot(&self.name, regions);
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    SaveSnapshot,
+    "snapshot save <name> [start,end | start,+length | mapping] = save memory (all writable mappings by default)"
+);
+
+#[derive(Debug)]
+pub struct DiffSnapshot {
+    pub name: String,
+}
+
+/// Most changed bytes shown for any one changed range
+const MAX_DIFF_BYTES: usize = 16;
+
+impl Execute for DiffSnapshot {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let snapshots = dbg.snapshots();
+        let regions = snapshots
+            .get(&self.name)
+            .ok_or("No snapshot by that name!")?;
+        let mut changes = 0;
+
+        for (start, old_bytes) in regions {
+            let Ok(new_bytes) = dbg.read_memory(pid, *start, old_bytes.len()) else {
+                println!(
+                    "{:#x}-{:#x} is no longer mapped",
+                    start,
+                    *start + old_bytes.len() as u64
+                );
+                continue;
+            };
+
+            for (offset, len) in changed_ranges(old_bytes, &new_bytes) {
+                let shown = offset..offset + len.min(MAX_DIFF_BYTES);
+                let ellipsis = if len > MAX_DIFF_BYTES { " ..." } else { "" };
+
+                println!(
+                    "{} ({} bytes): {}{} -> {}{}",
+                    dbg.describe_addr(pid, start + offset as u64),
+                    len,
+                    format_bytes(&old_bytes[shown.clone()]),
+                    ellipsis,
+                    format_bytes(&new_bytes[shown]),
+                    ellipsis
+                );
+                changes += 1;
+            }
+        }
+
+        if changes == 0 {
+            println!("No changes since snapshot \"{}\".", self.name);
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    DiffSnapshot,
+    "snapshot diff <name> = list every byte range changed since the snapshot"
+);
+
+#[derive(Debug)]
+pub struct DeleteSnapshot {
+    pub name: String,
+}
+
+impl Execute for DeleteSnapshot {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        dbg.delete_snapshot(&self.name)
+            .ok_or("No snapshot by that name!")?;
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    DeleteSnapshot,
+    "snapshot delete <name> = forget a saved snapshot"
+);
+
+#[derive(Debug)]
+pub struct ListSnapshots;
+
+impl Execute for ListSnapshots {
+    fn execute(&self, _pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let snapshots = dbg.snapshots();
+        if snapshots.is_empty() {
+            println!("No snapshots.");
+        }
+
+        let mut names: Vec<&String> = snapshots.keys().collect();
+        names.sort();
+
+        for name in names {
+            let size: usize = snapshots[name].iter().map(|(_, bytes)| bytes.len()).sum();
+            println!("{}: {} bytes", name, size);
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    ListSnapshots,
+    "snapshot list = list saved snapshots and their sizes"
+);
+
+/// Finds the runs of bytes that differ, as (offset, length) pairs
+fn changed_ranges(old_bytes: &[u8], new_bytes: &[u8]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for (offset, _) in old_bytes
+        .iter()
+        .zip(new_bytes)
+        .enumerate()
+        .filter(|(_, (old, new))| old != new)
+    {
+        match ranges.last_mut() {
+            Some((start, len)) if *start + *len == offset => *len += 1,
+            _ => ranges.push((offset, 1)),
+        }
+    }
+
+    ranges
+}
+
+fn format_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[derive(Debug)]
+pub struct InfoProcMappings {
+    pub addr: Option<u64>,
+}
+
+impl Execute for InfoProcMappings {
+    fn execute(&self, pid: Pid, dbg: &TraceeDbg) -> Result<TargetStat, &'static str> {
+        let mappings =
+            Mappings::from_pid(pid.into()).map_err(|_| "Failed to read the tracee's mappings!")?;
+        let objects = loaded_objects(&mappings);
+
+        let mappings: Vec<&Map> = mappings
+            .iter()
+            .filter(|map| match self.addr {
+                Some(addr) => (map.base as u64..map.ceiling as u64).contains(&addr),
+                None => true,
+            })
+            .collect();
+
+        if mappings.is_empty() {
+            return Err("No mapping contains that address!");
+        }
+
+        println!(
+            "{:>18} {:>18} {:>10} {:>10} {:<5} {:>9} Object / Sections",
+            "Start Addr", "End Addr", "Size", "Offset", "Perms", "Inode"
+        );
+
+        for map in mappings {
+            let (start, end) = (map.base as u64, map.ceiling as u64);
+
+            // Sections are matched up by address, so the .bss sitting in an
+            // anonymous mapping past the end of its file is named too
+            let sections: Vec<String> = objects
+                .iter()
+                .flat_map(|(path, bias, sections)| {
+                    sections
+                        .iter()
+                        .filter(move |section| {
+                            let section_start = section.addr.wrapping_add(*bias);
+                            section_start < end && start < section_start + section.size
+                        })
+                        .map(move |section| match map.pathname {
+                            Path::MappedFile(ref map_path) if map_path == path => {
+                                section.name.clone()
+                            }
+                            _ => format!(
+                                "{}:{}",
+                                path.rsplit('/').next().unwrap_or(path),
+                                section.name
+                            ),
+                        })
+                })
+                .collect();
+
+            let row = format!(
+                "{:#18x} {:#18x} {:#10x} {:#10x} {:<5} {:>9} {} {}",
+                start,
+                end,
+                end - start,
+                map.offset,
+                format_perms(&map.perms),
+                // procmaps parses the (decimal) inode as hex, undo that
+                format!("{:x}", map.inode),
+                format_map_path(map),
+                sections.join(" ")
+            );
+            println!("{}", row.trim_end());
+        }
+
+        if let Some(addr) = self.addr {
+            let section = objects.iter().find_map(|(path, bias, sections)| {
+                sections
+                    .iter()
+                    .find(|section| {
+                        let section_start = section.addr.wrapping_add(*bias);
+                        (section_start..section_start + section.size).contains(&addr)
+                    })
+                    .map(|section| (path, section))
+            });
+
+            match section {
+                Some((path, section)) => println!(
+                    "{} is in {} of {}",
+                    dbg.describe_addr(pid, addr),
+                    section.name,
+                    path
+                ),
+                None => println!(
+                    "{} is not in any loaded section",
+                    dbg.describe_addr(pid, addr)
+                ),
+            }
+        }
+
+        Ok(TargetStat::AwaitingCommand)
+    }
+}
+
+define_help!(
+    InfoProcMappings,
+    "i/info proc mappings [hex address] = list the tracee's memory mappings, or the one holding an address"
+);
+
+/// Fixed address of the legacy vsyscall page on x86-64
+const VSYSCALL_ADDR: usize = 0xffffffffff600000;
+
+/// Loads the section headers of every file mapped into the tracee, with
+/// the bias each was loaded at. The bias comes from lining up a mapping's
+/// file offset with a section stored there.
+fn loaded_objects(mappings: &Mappings) -> Vec<(String, u64, Vec<ElfSection>)> {
+    let mut objects: Vec<(String, u64, Vec<ElfSection>)> = Vec::new();
+
+    for map in mappings.iter() {
+        let Path::MappedFile(ref path) = map.pathname else {
+            continue;
+        };
+
+        if path.is_empty() || objects.iter().any(|(loaded, ..)| loaded == path) {
+            continue;
+        }
+
+        let Some(sections) = fs::read(path)
+            .ok()
+            .and_then(|buf| load_elf_sections(&buf).ok())
+        else {
+            continue;
+        };
+
+        let map_offset = map.offset as u64;
+        let map_size = (map.ceiling - map.base) as u64;
+
+        let bias = sections.iter().find_map(|section| {
+            let (offset, _) = section.file_range?;
+
+            (map_offset..map_offset + map_size)
+                .contains(&offset)
+                .then(|| (map.base as u64 + (offset - map_offset)).wrapping_sub(section.addr))
+        });
+
+        if let Some(bias) = bias {
+            objects.push((path.clone(), bias, sections));
+        }
+    }
+
+    objects
+}
+
+fn format_perms(perms: &Permissions) -> String {
+    format!(
+        "{}{}{}{}",
+        if perms.readable { 'r' } else { '-' },
+        if perms.writable { 'w' } else { '-' },
+        if perms.executable { 'x' } else { '-' },
+        if perms.privacy == Privacy::Shared {
+            's'
+        } else {
+            'p'
+        }
+    )
+}
+
+fn format_map_path(map: &Map) -> String {
+    match map.pathname {
+        Path::MappedFile(ref path) => path.clone(),
+        Path::Stack => String::from("[stack]"),
+        Path::ThreadStack(tid) => format!("[stack:{}]", tid),
+        Path::Vdso => String::from("[vdso]"),
+        Path::Heap => String::from("[heap]"),
+        // procmaps reads [vsyscall] as [vvar], but only the former is up here
+        Path::Vvar if map.base >= VSYSCALL_ADDR => String::from("[vsyscall]"),
+        Path::Vvar => String::from("[vvar]"),
+        Path::Vsyscall => String::from("[vsyscall]"),
+    }
+}
+
+pub fn get_segment_base_addr(pid: Pid) -> Result<usize, &'static str> {
+    Mappings::from_pid(pid.into())
+        .map_err(|_| "Failed to find segment base")?
+        .first()
         .map(|mem_region| mem_region.base)
         .ok_or("Failed to find segment base")
 }
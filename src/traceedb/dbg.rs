@@ -1,30 +1,122 @@
+use super::register::*;
+use super::signal::*;
 use super::symbol::*;
+use super::syscall::*;
+use super::unwind::*;
 use crate::traceedb::breakpoint::*;
+use crate::traceedb::catchpoint::*;
 use crate::traceedb::command::*;
+use crate::traceedb::disasm::*;
+use crate::traceedb::memory::{self, MemRegions};
+use crate::traceedb::thread::*;
 
 use gimli::Dwarf;
 use nix::{
+    libc::{self, user_regs_struct},
     sys::personality,
     sys::ptrace,
-    sys::signal::{kill, Signal},
-    sys::wait::{waitpid, WaitStatus},
-    unistd::{execv, fork, ForkResult, Pid},
+    sys::signal::{kill, sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal},
+    sys::wait::{waitpid, WaitPidFlag, WaitStatus},
+    unistd::{execv, fork, getpgrp, setpgid, tcsetpgrp, ForkResult, Pid},
 };
-use procmaps::Mappings;
+use object::{Object, ObjectKind};
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, Ref, RefCell};
+use std::collections::{HashMap, VecDeque};
 use std::ffi::{c_void, CStr, CString};
+use std::fs;
 use std::io::Write;
 use std::io::{stdin, stdout};
-use std::{borrow, ops::Index};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::{borrow, iter, ops::Index};
+
+/// The tracee, and the thread in it that Ctrl-C is forwarded to while it's
+/// running
+static TRACEE_PID: AtomicI32 = AtomicI32::new(0);
+static TRACEE_TID: AtomicI32 = AtomicI32::new(0);
+static TRACEE_RUNNING: AtomicBool = AtomicBool::new(false);
+static INTERRUPT_SENT: AtomicBool = AtomicBool::new(false);
+
+/// Ctrl-C stops the tracee with a SIGSTOP, which unlike SIGINT it can't
+/// ignore, landing us back at the prompt. At the prompt itself it's ignored
+/// rather than killing the debugger. A tracee we spawned has the terminal
+/// while it runs, so there the SIGINT goes to it instead.
+extern "C" fn interrupt_tracee(_: libc::c_int) {
+    if TRACEE_RUNNING.load(Ordering::SeqCst) {
+        INTERRUPT_SENT.store(true, Ordering::SeqCst);
+        tgkill(
+            Pid::from_raw(TRACEE_PID.load(Ordering::SeqCst)),
+            Pid::from_raw(TRACEE_TID.load(Ordering::SeqCst)),
+            Signal::SIGSTOP,
+        );
+    }
+}
+
+/// Makes a process group the terminal's foreground one, which reads its input
+/// and gets its Ctrl-C. There's nothing to do without a terminal.
+fn give_terminal_to(pgrp: Pid) {
+    let _ = tcsetpgrp(libc::STDIN_FILENO, pgrp);
+}
+
+/// Sends a signal to one thread of the tracee. Unlike kill(), a SIGSTOP sent
+/// this way is reported by that thread alone rather than stopping them all.
+fn tgkill(tgid: Pid, tid: Pid, signal: Signal) -> bool {
+    unsafe {
+        libc::syscall(
+            libc::SYS_tgkill,
+            tgid.as_raw(),
+            tid.as_raw(),
+            signal as libc::c_int,
+        ) == 0
+    }
+}
+
+/// What the wait loop takes up next instead of waiting on the tracee: an
+/// event that turned up while at the prompt for another stop in non-stop
+/// mode, or that prompt to go back to once the event's been dealt with
+enum QueuedStop {
+    Event(WaitStatus, WaitStatus),
+    Prompt(WaitStatus),
+}
+
+/// The temporary breakpoint a nexti plants after a call. It only ends the
+/// step for the thread that made the call, back in the frame it made it from,
+/// rather than for a deeper, recursive call returning to the same place.
+#[derive(Debug)]
+struct StepOver {
+    brkpt: BrkptRecord,
+    tid: Pid,
+    /// The stack pointer at the call, as it is again once it's returned
+    frame: u64,
+}
 
 #[derive(Debug)]
 pub struct TraceeDbg<'dwarf> {
     program: Option<String>,
+    attach_pid: Option<Pid>,
     breakpoints: RefCell<HashMap<u64, BrkptRecord>>,
+    brkpt_groups: RefCell<Vec<BrkptGroup>>,
     symbols: Option<RefCell<Dwarf<borrow::Cow<'dwarf, [u8]>>>>,
+    elf_symbols: Option<Vec<ElfSymbol>>,
+    unwind_info: Option<UnwindInfo<'dwarf>>,
     position_ind_p: bool,
+    inline_skip: Cell<usize>,
+    pending_steps: Cell<(usize, bool)>,
+    step_over: RefCell<Option<StepOver>>,
+    held_stops: RefCell<VecDeque<WaitStatus>>,
+    snapshots: RefCell<HashMap<String, MemRegions>>,
+    signal_policies: RefCell<HashMap<Signal, SignalPolicy>>,
+    threads: RefCell<Vec<TraceeThread>>,
+    current_thread: Cell<Pid>,
+    non_stop: Cell<bool>,
+    follow_fork_child: Cell<bool>,
+    detach_on_fork: Cell<bool>,
+    vfork_traps: RefCell<Vec<BrkptRecord>>,
+    catchpoints: RefCell<Vec<Catchpoint>>,
+    trace_syscalls: Cell<bool>,
+    /// The process group of a program we spawned, handed the terminal
+    /// whenever it runs
+    tracee_pgrp: Cell<Option<Pid>>,
 }
 
 impl<'dwarf> TraceeDbg<'dwarf> {
@@ -32,11 +124,1223 @@ impl<'dwarf> TraceeDbg<'dwarf> {
         TraceeBuilder::default()
     }
 
+    pub fn symbols(&self) -> Option<Ref<'_, Dwarf<borrow::Cow<'dwarf, [u8]>>>> {
+        self.symbols.as_ref().map(|symref| symref.borrow())
+    }
+
+    pub fn elf_symbols(&self) -> Option<&[ElfSymbol]> {
+        self.elf_symbols.as_deref()
+    }
+
+    pub fn breakpoint_groups(&self) -> Ref<'_, Vec<BrkptGroup>> {
+        self.brkpt_groups.borrow()
+    }
+
+    pub fn catchpoints(&self) -> Ref<'_, Vec<Catchpoint>> {
+        self.catchpoints.borrow()
+    }
+
+    /// Breakpoints and catchpoints are numbered in one sequence
+    fn next_brkpt_id(&self) -> usize {
+        self.brkpt_groups.borrow().len() + self.catchpoints.borrow().len() + 1
+    }
+
+    pub fn add_catchpoint(&self, kind: CatchKind) -> usize {
+        let id = self.next_brkpt_id();
+        self.catchpoints.borrow_mut().push(Catchpoint { id, kind });
+        id
+    }
+
+    /// Logs every syscall the tracee makes, strace style, without stopping
+    pub fn set_trace_syscalls(&self, enabled: bool) {
+        self.trace_syscalls.set(enabled);
+    }
+
+    /// Whether threads are continued with PTRACE_SYSCALL, stopping at each
+    /// syscall's entry and exit, rather than PTRACE_CONT
+    fn stops_at_syscalls(&self) -> bool {
+        self.trace_syscalls.get()
+            || self
+                .catchpoints()
+                .iter()
+                .any(|catchpoint| matches!(catchpoint.kind, CatchKind::Syscall(_)))
+    }
+
+    /// Follows a thread in and out of a syscall at its syscall stops, logging
+    /// it when tracing syscalls. Returns what to report if a catchpoint
+    /// caught it, otherwise it's to carry on.
+    fn syscall_stop(&self, tid: Pid) -> Option<String> {
+        let regs = ptrace::getregs(tid).ok()?;
+        let entry = self.with_thread(tid, |thread| thread.syscall.take())?;
+        let prefix = if self.threads().len() > 1 {
+            format!("[pid {}] ", tid)
+        } else {
+            String::new()
+        };
+
+        let (nr, call, ret) = match entry {
+            None => {
+                let nr = regs.orig_rax;
+                let args = [regs.rdi, regs.rsi, regs.rdx, regs.r10, regs.r8, regs.r9];
+                self.with_thread(tid, |thread| thread.syscall = Some((nr, args)));
+
+                let call = format_syscall(tid, self, nr, &args);
+                if self.trace_syscalls.get() && never_returns(nr) {
+                    println!("{}{} = ?", prefix, call);
+                }
+
+                (nr, call, None)
+            }
+
+            // Arguments are read again on the way out, buffers having been
+            // filled in by then
+            Some((nr, args)) => {
+                let call = format_syscall(tid, self, nr, &args);
+                let ret = format_return(nr, regs.rax as i64);
+                if self.trace_syscalls.get() {
+                    println!("{}{} = {}", prefix, call, ret);
+                }
+
+                (nr, call, Some(ret))
+            }
+        };
+
+        let id = self
+            .catchpoints()
+            .iter()
+            .find(|catchpoint| catchpoint.catches_syscall(nr))
+            .map(|catchpoint| catchpoint.id)?;
+
+        Some(match ret {
+            None => format!("Catchpoint {} (call to syscall {})", id, call),
+            Some(ret) => format!(
+                "Catchpoint {} (returned from syscall {} = {})",
+                id, call, ret
+            ),
+        })
+    }
+
+    /// Numbers the breakpoint locations at an address the way the breakpoint
+    /// list does, "2" for a lone location and "2.1", "2.2" for one of several.
+    pub fn brkpt_location_ids(&self, addr: u64) -> Vec<String> {
+        self.brkpt_groups
+            .borrow()
+            .iter()
+            .filter_map(|group| {
+                let idx = group.locations.iter().position(|loc| *loc == addr)?;
+
+                if group.locations.len() == 1 {
+                    Some(group.id.to_string())
+                } else {
+                    Some(format!("{}.{}", group.id, idx + 1))
+                }
+            })
+            .collect()
+    }
+
+    pub fn unwind_info(&self) -> Option<&UnwindInfo<'dwarf>> {
+        self.unwind_info.as_ref()
+    }
+
+    /// Number of inlined frames at the current pc that are still hidden,
+    /// i.e. that we're stopped at the very start of and haven't stepped into.
+    pub fn inline_skip(&self) -> usize {
+        self.inline_skip.get()
+    }
+
+    /// Steps into the outermost hidden inlined frame without moving the pc,
+    /// returning the name of the function entered.
+    pub fn step_into_inline(&self, pid: Pid) -> Option<String> {
+        let skip = self.inline_skip.get();
+        if skip == 0 {
+            return None;
+        }
+
+        let regs = ptrace::getregs(pid).ok()?;
+        let file_pc = regs.rip.wrapping_sub(self.load_bias(pid).ok()?);
+        let scope = function_scope_at(self.symbols()?, file_pc).ok()?;
+
+        self.inline_skip.set(skip - 1);
+        scope
+            .inlined
+            .iter()
+            .rev()
+            .nth(skip - 1)
+            .map(|call| call.name.clone())
+    }
+
+    fn reset_inline_skip(&self, pid: Pid) {
+        let skip = (|| {
+            let regs = ptrace::getregs(pid).ok()?;
+            let file_pc = regs.rip.wrapping_sub(self.load_bias(pid).ok()?);
+            let scope = function_scope_at(self.symbols()?, file_pc).ok()?;
+
+            // Calls we're sitting right at the start of are shown as the call site
+            Some(
+                scope
+                    .inlined
+                    .iter()
+                    .rev()
+                    .take_while(|call| call.entry_pc == file_pc)
+                    .count(),
+            )
+        })();
+
+        self.inline_skip.set(skip.unwrap_or(0));
+    }
+
+    /// Offset between the addresses recorded in the ELF/DWARF data and
+    /// where they actually live in the tracee, only non-zero for ET_DYN.
+    pub fn load_bias(&self, pid: Pid) -> Result<u64, &'static str> {
+        if self.position_ind_p {
+            get_segment_base_addr(pid).map(|base| base as u64)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Renders a runtime address as "name+offset" if a symbol covers it.
+    pub fn symbolize(&self, pid: Pid, addr: u64) -> Option<String> {
+        let file_addr = addr.wrapping_sub(self.load_bias(pid).ok()?);
+
+        match addr_to_symbol(self.elf_symbols()?, file_addr)? {
+            (sym, 0) => Some(sym.demangled.clone()),
+            (sym, offset) => Some(format!("{}+{}", sym.demangled, offset)),
+        }
+    }
+
+    /// Renders a runtime address as "0x1234 <name+offset>", or just the
+    /// address when no symbol covers it.
+    pub fn describe_addr(&self, pid: Pid, addr: u64) -> String {
+        match self.symbolize(pid, addr) {
+            Some(name) => format!("{:#x} <{}>", addr, name),
+            None => format!("{:#x}", addr),
+        }
+    }
+
+    /// Queues up further instruction steps to take, without prompting, after
+    /// the one about to be made.
+    pub fn set_pending_steps(&self, count: usize, over_calls: bool) {
+        self.pending_steps.set((count, over_calls));
+    }
+
+    /// Executes a single instruction. With `over_calls` a call is run to
+    /// completion instead, by planting a temporary breakpoint on the
+    /// instruction after it and continuing.
+    pub fn step_instruction(&self, pid: Pid, over_calls: bool) -> Result<TargetStat, &'static str> {
+        if !over_calls {
+            return self.resume_thread(pid, true).map(|_| TargetStat::Running);
+        }
+
+        let regs = ptrace::getregs(pid).map_err(|_| "failed to PTRACE_GETREGS")?;
+        let code = self
+            .read_code(pid, regs.rip, MAX_INSN_LEN)
+            .map_err(|_| "Cannot access tracee memory at the pc!")?;
+
+        match disassemble(&code, regs.rip, 1).first() {
+            Some(insn) if insn.is_call => {
+                // Where there's a breakpoint already its trap is what's
+                // kept, to go back when this one's taken out
+                let return_addr = insn.next_addr();
+                let brkptrec = BrkptRecord::new(pid, return_addr as *mut c_void)?;
+                brkptrec.activate();
+                *self.step_over.borrow_mut() = Some(StepOver {
+                    brkpt: brkptrec,
+                    tid: pid,
+                    frame: regs.rsp,
+                });
+
+                Continue { all: false }.execute(pid, self)
+            }
+
+            _ => self.resume_thread(pid, true).map(|_| TargetStat::Running),
+        }
+    }
+
+    pub fn signal_policy(&self, signal: Signal) -> SignalPolicy {
+        self.signal_policies
+            .borrow()
+            .get(&signal)
+            .copied()
+            .unwrap_or_else(|| SignalPolicy::default_for(signal))
+    }
+
+    pub fn set_signal_policy(&self, signal: Signal, policy: SignalPolicy) {
+        self.signal_policies.borrow_mut().insert(signal, policy);
+    }
+
+    /// Sets the signal the selected thread gets when it next resumes
+    pub fn set_pending_signal(&self, signal: Option<Signal>) {
+        let current = self.current_thread();
+        self.with_thread(current, |thread| thread.pending_signal = signal);
+    }
+
+    /// The tracee's threads, the main thread first
+    pub fn threads(&self) -> Ref<'_, Vec<TraceeThread>> {
+        self.threads.borrow()
+    }
+
+    /// The thread commands act on, the one that last stopped unless the
+    /// user picked another
+    pub fn current_thread(&self) -> Pid {
+        self.current_thread.get()
+    }
+
+    /// Makes the thread numbered `id` the one commands act on
+    pub fn select_thread(&self, id: usize) -> Result<Pid, &'static str> {
+        let tid = self
+            .threads()
+            .iter()
+            .find(|thread| thread.id == id)
+            .map(|thread| thread.tid)
+            .ok_or("No thread with that number!")?;
+
+        self.current_thread.set(tid);
+        self.reset_inline_skip(tid);
+        Ok(tid)
+    }
+
+    fn with_thread<R>(&self, tid: Pid, func: impl FnOnce(&mut TraceeThread) -> R) -> Option<R> {
+        self.threads
+            .borrow_mut()
+            .iter_mut()
+            .find(|thread| thread.tid == tid)
+            .map(func)
+    }
+
+    fn add_thread(&self, pid: Pid, tid: Pid, state: ThreadState) -> usize {
+        let mut threads = self.threads.borrow_mut();
+        let id = threads.last().map_or(1, |thread| thread.id + 1);
+
+        threads.push(TraceeThread::new(id, pid, tid, state));
+        id
+    }
+
+    /// The process a thread belongs to
+    pub fn process_of(&self, tid: Pid) -> Pid {
+        self.with_thread(tid, |thread| thread.pid).unwrap_or(tid)
+    }
+
+    /// Resumes a stopped thread with its pending signal, either freely or for
+    /// a single instruction
+    pub fn resume_thread(&self, tid: Pid, stepping: bool) -> Result<(), &'static str> {
+        if let Some(status) = self.step_past_trap(tid)? {
+            let stepped = matches!(status, WaitStatus::Stopped(_, Signal::SIGTRAP));
+
+            // The step's own stop, or whatever cut it short, is taken up by
+            // the wait loop next, as if it had just been waited on
+            if stepping || !stepped {
+                let state = if stepping {
+                    ThreadState::Stepping
+                } else {
+                    ThreadState::Running
+                };
+                self.with_thread(tid, |thread| thread.state = state);
+                self.held_stops.borrow_mut().push_back(status);
+                return Ok(());
+            }
+        }
+
+        let signal = self
+            .with_thread(tid, |thread| thread.pending_signal.take())
+            .flatten();
+
+        // Resuming any other way than PTRACE_SYSCALL skips the exit stop of
+        // a syscall it's in
+        let syscall_stops = !stepping && self.stops_at_syscalls();
+        if !syscall_stops {
+            self.with_thread(tid, |thread| thread.syscall = None);
+        }
+
+        let (state, result, err_msg) = if stepping {
+            (
+                ThreadState::Stepping,
+                ptrace::step(tid, signal),
+                "failed to PTRACE_SINGLESTEP",
+            )
+        } else if syscall_stops {
+            (
+                ThreadState::Running,
+                ptrace::syscall(tid, signal),
+                "failed to PTRACE_SYSCALL",
+            )
+        } else {
+            (
+                ThreadState::Running,
+                ptrace::cont(tid, signal),
+                "failed to PTRACE_CONT",
+            )
+        };
+
+        result.map_err(|err_no| {
+            eprintln!("ERRNO {}", err_no);
+            err_msg
+        })?;
+
+        self.with_thread(tid, |thread| thread.state = state);
+        Ok(())
+    }
+
+    /// Steps a thread that's stopped on one of our traps past it, putting
+    /// the original byte back for the one instruction and the trap after,
+    /// so the breakpoint stays armed for every thread. Threads left running
+    /// in non-stop mode can go through it untrapped in the meantime. Returns
+    /// the stop the step ended in, if it was taken.
+    fn step_past_trap(&self, tid: Pid) -> Result<Option<WaitStatus>, &'static str> {
+        let Some(addr) = self
+            .with_thread(tid, |thread| thread.trap_hit.take())
+            .flatten()
+        else {
+            return Ok(None);
+        };
+
+        // Unless the pc's been moved off it since, or the trap taken out
+        let regs = ptrace::getregs(tid).map_err(|_| "failed to PTRACE_GETREGS")?;
+        let original = self
+            .breakpoints
+            .borrow()
+            .get(&(addr + 1))
+            .or(self
+                .step_over
+                .borrow()
+                .as_ref()
+                .map(|step_over| &step_over.brkpt)
+                .filter(|brkpt| brkpt.pc_addr as u64 == addr))
+            .map(|brkpt| brkpt.original_insn as u8);
+
+        let Some(original) = original.filter(|_| regs.rip == addr) else {
+            return Ok(None);
+        };
+        if memory::read_bytes(tid, addr, 1)? != [0xCC] {
+            return Ok(None);
+        }
+
+        memory::write_bytes(tid, addr, &[original])?;
+        let status = ptrace::step(tid, None).and_then(|_| waitpid(tid, Some(WaitPidFlag::__WALL)));
+        let _ = memory::write_bytes(tid, addr, &[0xCC]);
+
+        // A signal that arrived first leaves it where it was
+        if ptrace::getregs(tid).is_ok_and(|regs| regs.rip == addr) {
+            self.with_thread(tid, |thread| thread.trap_hit = Some(addr));
+        }
+
+        status.map(Some).map_err(|err_no| {
+            eprintln!("ERRNO {}", err_no);
+            "failed to step past a breakpoint"
+        })
+    }
+
+    /// The address of the trap of ours a thread stopped with SIGTRAP has just
+    /// hit, if it has. An int3 is told apart from the end of a single step,
+    /// which can leave the pc in the same place, by its si_code.
+    fn trap_hit_at(&self, tid: Pid, rip: u64) -> Option<u64> {
+        let addr = rip.wrapping_sub(1);
+        let ours = self.breakpoints.borrow().contains_key(&rip)
+            || self
+                .step_over
+                .borrow()
+                .as_ref()
+                .is_some_and(|step_over| step_over.brkpt.pc_addr as u64 == addr);
+        let int3 = ptrace::getsiginfo(tid).is_ok_and(|info| info.si_code == libc::SI_KERNEL);
+
+        (ours && int3).then_some(addr)
+    }
+
+    /// Puts the pc of a thread that hit one of our traps back on the trapped
+    /// instruction, to be stepped past when it resumes
+    fn rewind_to_trap(&self, tid: Pid, addr: u64) {
+        let mut regs = ptrace::getregs(tid).expect("FATAL: Failed to send PTRACE_GETREGS");
+        regs.rip = addr;
+        ptrace::setregs(tid, regs).expect("FATAL: Failed to send message PTRACE_SETREGS");
+        self.with_thread(tid, |thread| thread.trap_hit = Some(addr));
+    }
+
+    /// Whether a trap hit is only a nexti's, and not one that ends it
+    fn passes_step_over(&self, tid: Pid, addr: u64, rsp: u64) -> bool {
+        let Some(ref step_over) = *self.step_over.borrow() else {
+            return false;
+        };
+
+        step_over.brkpt.pc_addr as u64 == addr
+            && (step_over.tid != tid || rsp < step_over.frame)
+            && !self.breakpoints.borrow().contains_key(&(addr + 1))
+    }
+
+    /// Whether a stop only halts the thread it happened in, leaving the rest
+    /// running, rather than the whole process
+    pub fn non_stop(&self) -> bool {
+        self.non_stop.get()
+    }
+
+    /// Switches between non-stop and all-stop mode. Going back to all-stop
+    /// halts whatever was left running.
+    pub fn set_non_stop(&self, enabled: bool) {
+        self.non_stop.set(enabled);
+
+        if !enabled {
+            self.stop_other_threads(self.current_thread());
+        }
+    }
+
+    /// Continues a thread and every other stopped thread of its process
+    /// along with it. Processes kept after a fork stay where they are until
+    /// they're selected and continued themselves.
+    pub fn continue_all(&self, pid: Pid) -> Result<(), &'static str> {
+        let process = self.process_of(pid);
+        let stopped: Vec<Pid> = self
+            .threads()
+            .iter()
+            .filter(|thread| thread.pid == process && thread.tid != pid)
+            .filter(|thread| thread.state == ThreadState::Stopped)
+            .map(|thread| thread.tid)
+            .collect();
+
+        // It goes first, the others not running yet while it's stepped
+        // past a breakpoint it's stopped on
+        self.resume_thread(pid, false)?;
+
+        for tid in stopped {
+            self.resume_thread(tid, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Picks a thread up the way it was going before a stop we handled
+    /// ourselves
+    fn resume_as_before(&self, tid: Pid) -> Result<(), &'static str> {
+        match self.with_thread(tid, |thread| thread.state) {
+            Some(ThreadState::Running) => self.resume_thread(tid, false),
+            Some(ThreadState::Stepping) => self.resume_thread(tid, true),
+            _ => Ok(()),
+        }
+    }
+
+    /// Keeps the thread list up to date, dealing with the stops that only
+    /// concern it. Returns whether the event was handled, leaving the
+    /// tracee running as it was.
+    fn handle_thread_event(&self, status: WaitStatus) -> bool {
+        let Some(tid) = status.pid() else {
+            return false;
+        };
+        let known = self.with_thread(tid, |thread| thread.stop_requested);
+
+        match (status, known) {
+            // Threads that went with an exec, already forgotten
+            (WaitStatus::Exited(..) | WaitStatus::Signaled(..), None) => true,
+
+            (WaitStatus::Exited(..) | WaitStatus::Signaled(..), Some(_)) => {
+                let pid = self.process_of(tid);
+
+                if tid != pid {
+                    let exited = self.threads().iter().position(|thread| thread.tid == tid);
+
+                    if let Some(idx) = exited {
+                        let thread = self.threads.borrow_mut().remove(idx);
+                        println!("[Thread {} (LWP {}) exited]", thread.id, thread.tid);
+                    }
+                } else if self.threads().iter().any(|thread| thread.pid != pid) {
+                    // Other processes kept after a fork carry on without it
+                    self.threads.borrow_mut().retain(|thread| thread.pid != pid);
+
+                    match status {
+                        WaitStatus::Exited(_, code) => {
+                            println!("[Process {} exited with code {}]", pid, code)
+                        }
+                        _ => println!("[Process {} was terminated]", pid),
+                    }
+                } else {
+                    // The last process, ending the session
+                    return false;
+                }
+
+                if self.with_thread(self.current_thread(), |_| ()).is_none() {
+                    let threads = self.threads();
+                    let next = threads
+                        .iter()
+                        .find(|thread| thread.pid == pid)
+                        .or_else(|| threads.first())
+                        .map(|thread| thread.tid);
+                    drop(threads);
+                    self.current_thread.set(next.unwrap_or(pid));
+                }
+
+                true
+            }
+
+            // A new thread's first stop can arrive ahead of the clone event
+            // announcing it
+            (_, None) => {
+                let pid = thread_group_of(tid).unwrap_or(tid);
+
+                // Likewise a forked child's, which waits on the fork event to
+                // see what becomes of it
+                if pid == tid {
+                    self.add_thread(pid, tid, ThreadState::Stopped);
+                    return true;
+                }
+
+                let running = self
+                    .threads()
+                    .iter()
+                    .any(|thread| thread.pid == pid && thread.state == ThreadState::Running);
+                let id = self.add_thread(pid, tid, ThreadState::Stopped);
+                println!("[New thread {} (LWP {})]", id, tid);
+
+                if running {
+                    let _ = self.resume_thread(tid, false);
+                }
+
+                true
+            }
+
+            // The stop we asked for when halting it, arriving late
+            (WaitStatus::Stopped(_, Signal::SIGSTOP), Some(true)) => {
+                self.with_thread(tid, |thread| thread.stop_requested = false);
+                let _ = self.resume_as_before(tid);
+                true
+            }
+
+            (WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE), _) => {
+                self.track_new_thread(tid);
+                let _ = self.resume_as_before(tid);
+                true
+            }
+
+            (
+                WaitStatus::PtraceEvent(
+                    _,
+                    _,
+                    event @ (libc::PTRACE_EVENT_FORK | libc::PTRACE_EVENT_VFORK),
+                ),
+                _,
+            ) => {
+                let followed = self.follow_fork(tid, event == libc::PTRACE_EVENT_VFORK);
+                let _ = self.resume_as_before(followed);
+                true
+            }
+
+            // A vfork child let go has stopped sharing the parent's memory,
+            // so the traps taken out for it can go back
+            (WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_VFORK_DONE), _) => {
+                for brkpt in self.vfork_traps.borrow_mut().drain(..) {
+                    let _ = memory::write_bytes(tid, brkpt.pc_addr as u64, &[0xCC]);
+                }
+
+                let _ = self.resume_as_before(tid);
+                true
+            }
+
+            _ => false,
+        }
+    }
+
+    /// Decides what becomes of a process forked by the tracee, per
+    /// follow-fork-mode and detach-on-fork. Returns the thread that carries
+    /// on where the parent left off: the parent itself, or the child taking
+    /// over its state when that's followed.
+    fn follow_fork(&self, parent_tid: Pid, vfork: bool) -> Pid {
+        let Ok(child) = ptrace::getevent(parent_tid).map(|pid| Pid::from_raw(pid as i32)) else {
+            return parent_tid;
+        };
+        let parent = self.process_of(parent_tid);
+
+        // It starts out stopped, unless that stop's already been seen
+        if self.with_thread(child, |_| ()).is_none() {
+            let _ = waitpid(child, Some(WaitPidFlag::__WALL));
+            self.add_thread(child, child, ThreadState::Stopped);
+        }
+
+        if !self.follow_fork_child.get() {
+            if self.detach_on_fork.get() {
+                println!("[Detaching after fork from child process {}]", child);
+                self.detach_process(child, vfork);
+            } else {
+                println!("[Keeping child process {} stopped after fork]", child);
+            }
+
+            return parent_tid;
+        }
+
+        println!(
+            "[Attaching after process {} fork to child process {}]",
+            parent, child
+        );
+
+        let state = self.with_thread(parent_tid, |thread| thread.state);
+        self.with_thread(parent_tid, |thread| thread.state = ThreadState::Stopped);
+
+        if self.detach_on_fork.get() {
+            println!("[Detaching after fork from parent process {}]", parent);
+            self.detach_process(parent, vfork);
+        } else {
+            let running: Vec<Pid> = self
+                .threads()
+                .iter()
+                .filter(|thread| thread.pid == parent && thread.state != ThreadState::Stopped)
+                .map(|thread| thread.tid)
+                .collect();
+            self.halt_threads(&running);
+        }
+
+        // Breakpoints belong to whichever process is being debugged
+        for brkpt in self.breakpoints.borrow_mut().values_mut() {
+            brkpt.pid = child;
+        }
+        if let Some(step_over) = self.step_over.borrow_mut().as_mut() {
+            step_over.brkpt.pid = child;
+            if step_over.tid == parent_tid {
+                step_over.tid = child;
+            }
+        }
+
+        self.current_thread.set(child);
+        if let Some(state) = state {
+            self.with_thread(child, |thread| thread.state = state);
+        }
+
+        child
+    }
+
+    /// Lets a process go, taking our traps out of its memory first so it
+    /// doesn't trip over them untraced. A vfork child shares its parent's
+    /// memory until it execs or exits, so with `vfork` the traps are kept
+    /// aside to go back in once it does.
+    pub fn detach_process(&self, pid: Pid, vfork: bool) {
+        let running: Vec<Pid> = self
+            .threads()
+            .iter()
+            .filter(|thread| thread.pid == pid && thread.state != ThreadState::Stopped)
+            .map(|thread| thread.tid)
+            .collect();
+        self.halt_threads(&running);
+
+        let brkpts = self.breakpoints.borrow();
+        let step_over = self.step_over.borrow();
+
+        for brkpt in brkpts
+            .values()
+            .chain(step_over.as_ref().map(|step_over| &step_over.brkpt))
+        {
+            let addr = brkpt.pc_addr as u64;
+
+            if memory::read_bytes(pid, addr, 1).is_ok_and(|byte| byte[0] == 0xCC) {
+                let _ = memory::write_bytes(pid, addr, &[brkpt.original_insn as u8]);
+
+                if vfork {
+                    self.vfork_traps.borrow_mut().push(BrkptRecord {
+                        pid: brkpt.pid,
+                        pc_addr: brkpt.pc_addr,
+                        original_insn: brkpt.original_insn,
+                    });
+                }
+            }
+        }
+
+        self.collect_stop_requests(pid);
+
+        for thread in self.threads().iter().filter(|thread| thread.pid == pid) {
+            let _ = ptrace::detach(thread.tid, thread.pending_signal);
+        }
+        self.threads.borrow_mut().retain(|thread| thread.pid != pid);
+    }
+
+    /// A SIGSTOP sent to halt a thread that stopped for something else first
+    /// is still queued, and would stop the process again once it's let go,
+    /// so each thread is run on until it turns up. Our traps are out by now.
+    fn collect_stop_requests(&self, pid: Pid) {
+        let tids: Vec<Pid> = self
+            .threads()
+            .iter()
+            .filter(|thread| thread.pid == pid && thread.stop_requested)
+            .map(|thread| thread.tid)
+            .collect();
+
+        for tid in tids {
+            while ptrace::cont(tid, None).is_ok() {
+                match waitpid(tid, Some(WaitPidFlag::__WALL)) {
+                    Ok(WaitStatus::Stopped(_, Signal::SIGSTOP)) => break,
+
+                    Ok(WaitStatus::Stopped(_, signal)) => {
+                        let pass = self.signal_policy(signal).pass;
+                        self.with_thread(tid, |thread| {
+                            thread.pending_signal = Some(signal).filter(|_| pass)
+                        });
+                    }
+
+                    Ok(WaitStatus::Exited(..) | WaitStatus::Signaled(..)) | Err(_) => break,
+
+                    Ok(_) => {}
+                }
+            }
+
+            self.with_thread(tid, |thread| thread.stop_requested = false);
+        }
+    }
+
+    pub fn set_follow_fork_child(&self, enabled: bool) {
+        self.follow_fork_child.set(enabled);
+    }
+
+    pub fn set_detach_on_fork(&self, enabled: bool) {
+        self.detach_on_fork.set(enabled);
+    }
+
+    /// Every process being traced, the one started or attached to and any
+    /// kept after forking
+    pub fn processes(&self) -> Vec<Pid> {
+        let mut pids = Vec::new();
+
+        for thread in self.threads().iter() {
+            if !pids.contains(&thread.pid) {
+                pids.push(thread.pid);
+            }
+        }
+
+        pids
+    }
+
+    /// A process has replaced its program. Its other threads are gone and
+    /// the thread that ran exec carries on as the main one; if it's the
+    /// process being debugged, the new program's symbols are loaded and
+    /// breakpoints resolved against it afresh. Returns the new program's
+    /// path.
+    fn follow_exec(&mut self, pid: Pid) -> Option<String> {
+        let former = ptrace::getevent(pid)
+            .map(|tid| Pid::from_raw(tid as i32))
+            .unwrap_or(pid);
+        let state = self.with_thread(former, |thread| thread.state);
+        let current = self.process_of(self.current_thread()) == pid;
+
+        self.threads
+            .borrow_mut()
+            .retain(|thread| thread.pid != pid || thread.tid == pid);
+        if let Some(state) = state {
+            self.with_thread(pid, |thread| thread.state = state);
+        }
+        if current {
+            self.current_thread.set(pid);
+        }
+
+        let path = fs::read_link(format!("/proc/{}/exe", pid)).ok()?;
+        let path = path.to_string_lossy().into_owned();
+        println!("process {} is executing new program: {}", pid, path);
+
+        if current {
+            *self.step_over.borrow_mut() = None;
+            if let Err(err_msg) = self.load_program(&path) {
+                eprintln!("Err: {}", err_msg);
+            }
+            self.reset_breakpoints(pid);
+        }
+
+        Some(path)
+    }
+
+    /// Swaps in the symbols, unwind info and load kind of a new program.
+    /// These borrow from the file's bytes for as long as the debugger runs,
+    /// as the first program's do.
+    fn load_program(&mut self, path: &str) -> Result<(), &'static str> {
+        let elf_buf = fs::read(path).map_err(|_| "Failed to read the new program!")?;
+        let file =
+            object::File::parse(&*elf_buf).map_err(|_| "Failed to parse new program as ELF!")?;
+
+        // The sections we keep are copied out, so the file's buffer (and the
+        // previous program's sections) are freed rather than kept for good
+        self.position_ind_p = file.kind() == ObjectKind::Dynamic;
+        self.symbols = load_dwarf_data(&elf_buf).ok().map(|dwarf| {
+            RefCell::new(dwarf.borrow(|section| borrow::Cow::Owned(section.to_vec())))
+        });
+        self.elf_symbols = load_elf_symbols(&elf_buf).ok();
+        self.unwind_info = load_unwind_info(&elf_buf).ok().map(UnwindInfo::into_owned);
+        self.program = Some(path.to_string());
+
+        if self.symbols.is_none() {
+            println!("WARNING: No debug symbols loaded!")
+        }
+
+        Ok(())
+    }
+
+    /// Re-plants every breakpoint in a freshly exec'd program, leaving the
+    /// ones that no longer resolve pending
+    fn reset_breakpoints(&self, pid: Pid) {
+        self.breakpoints.borrow_mut().clear();
+        let load_bias = self.load_bias(pid).unwrap_or(0);
+
+        for group in self.brkpt_groups.borrow_mut().iter_mut() {
+            let addrs = self
+                .resolve_location(&group.spec)
+                .map(|(_, addrs)| addrs)
+                .unwrap_or_default();
+
+            group.locations = self.plant_traps(pid, addrs.iter().map(|addr| addr + load_bias));
+        }
+
+        for catchpoint in self.catchpoints.borrow_mut().iter_mut() {
+            if let CatchKind::Exception { thrown, locations } = &mut catchpoint.kind {
+                *locations = self.plant_exception_traps(pid, *thrown);
+            }
+        }
+    }
+
+    /// Sets traps at runtime addresses, sharing any already there, returning
+    /// the ones trapped
+    fn plant_traps(&self, pid: Pid, addrs: impl Iterator<Item = u64>) -> Vec<u64> {
+        let mut planted = Vec::new();
+
+        for addr in addrs {
+            if self.breakpoints.borrow().contains_key(&(addr + 1)) {
+                planted.push(addr);
+                continue;
+            }
+
+            let Ok(brkptrec) = BrkptRecord::new(pid, addr as *mut c_void) else {
+                continue;
+            };
+            brkptrec.activate();
+            planted.push(addr);
+            self.breakpoints.borrow_mut().insert(addr + 1, brkptrec);
+        }
+
+        planted
+    }
+
+    /// Traps the functions exceptions are thrown or caught through, whether
+    /// linked in or called through the PLT
+    fn plant_exception_traps(&self, pid: Pid, thrown: bool) -> Vec<u64> {
+        let Some(symbols) = self.elf_symbols() else {
+            return Vec::new();
+        };
+        let names = if thrown {
+            THROW_FUNCTIONS
+        } else {
+            CATCH_FUNCTIONS
+        };
+        let load_bias = self.load_bias(pid).unwrap_or(0);
+
+        let addrs = names
+            .iter()
+            .flat_map(|name| [name.to_string(), format!("{}@plt", name)])
+            .flat_map(|name| symbol_name_to_addrs(symbols, &name))
+            .map(|sym| sym.addr + load_bias);
+
+        self.plant_traps(pid, addrs)
+    }
+
+    pub fn add_exception_catchpoint(&self, pid: Pid, thrown: bool) -> Result<usize, &'static str> {
+        let locations = self.plant_exception_traps(pid, thrown);
+
+        if locations.is_empty() {
+            return Err("The program has no C++ exception or Rust panic functions to catch!");
+        }
+
+        Ok(self.add_catchpoint(CatchKind::Exception { thrown, locations }))
+    }
+
+    /// The first catchpoint that catches something, by its id
+    fn catchpoint_id(&self, catches: impl Fn(&Catchpoint) -> bool) -> Option<usize> {
+        self.catchpoints()
+            .iter()
+            .find(|catchpoint| catches(catchpoint))
+            .map(|catchpoint| catchpoint.id)
+    }
+
+    /// Resolves a breakpoint's "file:line" or function name to the file
+    /// addresses to trap at, along with the spec to record it under
+    fn resolve_location(&self, arg: &str) -> Result<(String, Vec<u64>), &'static str> {
+        if !is_file_and_lineno(arg) {
+            return self
+                .resolve_function(arg)
+                .map(|addrs| (arg.to_string(), addrs));
+        }
+
+        let Some(ref symref) = self.symbols else {
+            return Err("Cannot resolve source lines without debug symbols!");
+        };
+
+        let (fname, lno) = parse_file_and_lineno(arg)?;
+        let (used_line, addrs) = src_line_to_addrs(symref.borrow(), fname, lno)
+            .map_err(|_| "Failed to resolve address!")?;
+
+        if used_line != lno {
+            println!(
+                "Line {} of {} has no code, using line {} instead",
+                lno, fname, used_line
+            );
+        }
+
+        Ok((format!("{}:{}", fname, used_line), addrs))
+    }
+
+    /// Adds the thread a clone event announced, unless its first stop got to
+    /// us first. New threads run alongside their creator unless it was the
+    /// only one let go, for a step.
+    fn track_new_thread(&self, creator: Pid) {
+        let Ok(new_tid) = ptrace::getevent(creator).map(|tid| Pid::from_raw(tid as i32)) else {
+            return;
+        };
+
+        if self.with_thread(new_tid, |_| ()).is_some() {
+            return;
+        }
+
+        // It starts out stopped, so there's a stop to collect
+        let _ = waitpid(new_tid, Some(WaitPidFlag::__WALL));
+        let id = self.add_thread(self.process_of(creator), new_tid, ThreadState::Stopped);
+        println!("[New thread {} (LWP {})]", id, new_tid);
+
+        if self.with_thread(creator, |thread| thread.state) == Some(ThreadState::Running) {
+            let _ = self.resume_thread(new_tid, false);
+        }
+    }
+
+    /// Halts every other running thread, so the whole process is stopped
+    /// while the user looks at it
+    fn stop_other_threads(&self, tid: Pid) {
+        let running: Vec<Pid> = self
+            .threads()
+            .iter()
+            .filter(|thread| thread.tid != tid && thread.state != ThreadState::Stopped)
+            .map(|thread| thread.tid)
+            .collect();
+
+        self.halt_threads(&running);
+    }
+
+    /// Stops running threads and waits until they have. A thread that hits a
+    /// breakpoint of its own on the way has it undone, to hit again once it's
+    /// resumed.
+    pub fn halt_threads(&self, tids: &[Pid]) {
+        for &other in tids {
+            // One still on its way from halting it before does, another would
+            // be left over to stop it again later
+            let requested = self.with_thread(other, |thread| thread.stop_requested);
+            if requested != Some(true) && !tgkill(self.process_of(other), other, Signal::SIGSTOP) {
+                continue;
+            }
+            self.with_thread(other, |thread| thread.stop_requested = true);
+
+            loop {
+                match waitpid(other, Some(WaitPidFlag::__WALL)) {
+                    Ok(WaitStatus::Stopped(_, Signal::SIGSTOP)) => {
+                        self.with_thread(other, |thread| thread.stop_requested = false);
+                    }
+
+                    Ok(WaitStatus::Stopped(_, Signal::SIGTRAP)) => {
+                        if let Ok(mut regs) = ptrace::getregs(other) {
+                            if let Some(addr) = self.trap_hit_at(other, regs.rip) {
+                                regs.rip = addr;
+                                let _ = ptrace::setregs(other, regs);
+                            }
+                        }
+                    }
+
+                    // Signals that beat ours are held until it resumes
+                    Ok(WaitStatus::Stopped(_, signal)) => {
+                        let pass = self.signal_policy(signal).pass;
+                        self.with_thread(other, |thread| {
+                            thread.pending_signal = Some(signal).filter(|_| pass)
+                        });
+                    }
+
+                    Ok(WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE)) => {
+                        self.with_thread(other, |thread| thread.state = ThreadState::Stopped);
+                        self.track_new_thread(other);
+                    }
+
+                    // Still logged if tracing, but a catchpoint it hits goes
+                    // unreported while halting
+                    Ok(WaitStatus::PtraceSyscall(_)) => {
+                        self.syscall_stop(other);
+                    }
+
+                    Ok(WaitStatus::PtraceEvent(
+                        _,
+                        _,
+                        event @ (libc::PTRACE_EVENT_FORK | libc::PTRACE_EVENT_VFORK),
+                    )) => {
+                        self.with_thread(other, |thread| thread.state = ThreadState::Stopped);
+                        self.follow_fork(other, event == libc::PTRACE_EVENT_VFORK);
+                    }
+
+                    // A process's main thread is kept until its exit status
+                    // is collected in the wait loop
+                    Ok(WaitStatus::Exited(..) | WaitStatus::Signaled(..)) => {
+                        self.threads
+                            .borrow_mut()
+                            .retain(|thread| thread.tid != other || thread.tid == thread.pid);
+                    }
+
+                    Ok(WaitStatus::Continued(_) | WaitStatus::StillAlive) => continue,
+
+                    Ok(_) | Err(_) => {}
+                }
+
+                break;
+            }
+
+            self.with_thread(other, |thread| thread.state = ThreadState::Stopped);
+        }
+    }
+
+    /// Once the thread being run has exited there may be nothing left to
+    /// wait for, in non-stop mode or with processes kept stopped after a
+    /// fork, so the user is given back the prompt for a thread that's still
+    /// stopped, if there is one
+    fn prompt_after_exit(&self, waited_on: Pid) -> Option<WaitStatus> {
+        if self.with_thread(waited_on, |_| ()).is_some() {
+            return None;
+        }
+
+        let running = self
+            .threads()
+            .iter()
+            .any(|thread| thread.state != ThreadState::Stopped);
+        if !self.non_stop() && running {
+            return None;
+        }
+
+        let stopped = self
+            .threads()
+            .iter()
+            .find(|thread| thread.state == ThreadState::Stopped)
+            .map(|thread| thread.tid)?;
+
+        self.switch_to_thread(stopped);
+        // Stands in for the stop it already reported, the prompt doesn't
+        // look at it beyond which thread it's for
+        Some(WaitStatus::Stopped(stopped, Signal::SIGSTOP))
+    }
+
+    /// Makes the thread that stopped the current one, saying so when that
+    /// changes which thread commands act on
+    fn switch_to_thread(&self, tid: Pid) {
+        if self.current_thread() != tid && self.threads().len() > 1 {
+            if let Some(id) = self.with_thread(tid, |thread| thread.id) {
+                println!("[Switching to thread {} (LWP {})]", id, tid);
+            }
+        }
+
+        self.current_thread.set(tid);
+    }
+
+    /// Saved copies of memory regions, by name, as (start address, bytes)
+    pub fn snapshots(&self) -> Ref<'_, HashMap<String, MemRegions>> {
+        self.snapshots.borrow()
+    }
+
+    pub fn save_snapshot(&self, name: &str, regions: MemRegions) {
+        self.snapshots
+            .borrow_mut()
+            .insert(name.to_string(), regions);
+    }
+
+    pub fn delete_snapshot(&self, name: &str) -> Option<()> {
+        self.snapshots.borrow_mut().remove(name).map(|_| ())
+    }
+
+    /// Reads tracee memory with any of our armed breakpoint traps swapped back
+    /// out for the original bytes they replaced.
+    pub fn read_memory(&self, pid: Pid, addr: u64, len: usize) -> Result<Vec<u8>, &'static str> {
+        let mut bytes = memory::read_bytes(pid, addr, len)?;
+
+        // A nexti's trap goes first, as one planted over a breakpoint holds
+        // that breakpoint's trap as its original byte
+        let step_over = self.step_over.borrow();
+        let breakpoints = self.breakpoints.borrow();
+        let traps = step_over
+            .iter()
+            .map(|step_over| &step_over.brkpt)
+            .chain(breakpoints.values());
+
+        for brkpt in traps {
+            let brkpt_addr = brkpt.pc_addr as u64;
+
+            if (addr..addr + len as u64).contains(&brkpt_addr) {
+                let byte = &mut bytes[(brkpt_addr - addr) as usize];
+
+                // A trap lifted to step a thread past it has its byte back
+                if *byte == 0xCC {
+                    *byte = brkpt.original_insn as u8;
+                }
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Reads code for disassembly. Instructions near the end of a mapping
+    /// have fewer bytes after them than asked for, so this falls back to
+    /// however many pages of the range can be read.
+    pub fn read_code(&self, pid: Pid, addr: u64, len: usize) -> Result<Vec<u8>, &'static str> {
+        const PAGE_SIZE: u64 = 4096;
+
+        let end = addr
+            .checked_add(len as u64)
+            .ok_or("Cannot access tracee memory at that address!")?;
+        let page_ends = (addr / PAGE_SIZE + 1..=(end - 1) / PAGE_SIZE)
+            .rev()
+            .map(|page| page * PAGE_SIZE - addr);
+
+        iter::once(len as u64)
+            .chain(page_ends)
+            .find_map(|len| self.read_memory(pid, addr, len as usize).ok())
+            .ok_or("Cannot access tracee memory at that address!")
+    }
+
+    /// Writes tracee memory without disturbing armed breakpoints, new bytes
+    /// that land under a trap are kept aside to be restored when it's lifted.
+    pub fn write_memory(&self, pid: Pid, addr: u64, bytes: &[u8]) -> Result<(), &'static str> {
+        let mut bytes = bytes.to_vec();
+        let current = memory::read_bytes(pid, addr, bytes.len())?;
+
+        for brkpt in self.breakpoints.borrow_mut().values_mut() {
+            let brkpt_addr = brkpt.pc_addr as u64;
+
+            if (addr..addr + bytes.len() as u64).contains(&brkpt_addr) {
+                let offset = (brkpt_addr - addr) as usize;
+
+                if current[offset] == 0xCC {
+                    brkpt.original_insn = (brkpt.original_insn & !0xFF) | bytes[offset] as i64;
+                    bytes[offset] = 0xCC;
+                }
+            }
+        }
+
+        // Likewise for a nexti's trap, unless it's planted over a breakpoint
+        // that's already kept the new byte aside
+        if let Some(ref mut step_over) = *self.step_over.borrow_mut() {
+            let brkpt = &mut step_over.brkpt;
+            let brkpt_addr = brkpt.pc_addr as u64;
+
+            if (addr..addr + bytes.len() as u64).contains(&brkpt_addr) {
+                let offset = (brkpt_addr - addr) as usize;
+
+                if current[offset] == 0xCC && brkpt.original_insn as u8 != 0xCC {
+                    brkpt.original_insn = (brkpt.original_insn & !0xFF) | bytes[offset] as i64;
+                    bytes[offset] = 0xCC;
+                }
+            }
+        }
+
+        memory::write_bytes(pid, addr, &bytes)
+    }
+
     pub fn run(mut self) {
-        if self.program.is_some() {
+        if let Some(target_pid) = self.attach_pid {
+            self.attach_process(target_pid);
+        } else if self.program.is_some() {
             match unsafe { fork() } {
                 Ok(ForkResult::Parent { child, .. }) => {
                     println!("Spawned child process {}", child);
+
+                    // Also done here, so the group exists before we first
+                    // hand it the terminal
+                    let _ = setpgid(child, child);
+                    self.tracee_pgrp.set(Some(child));
+
+                    self.add_thread(child, child, ThreadState::Running);
                     self.run_debugger(child);
                 }
 
@@ -49,10 +1353,73 @@ impl<'dwarf> TraceeDbg<'dwarf> {
             }
         } else {
             let target_pid = run_get_pid_dialogue();
+            self.attach_process(target_pid);
+        }
+    }
 
-            ptrace::attach(target_pid).expect("Failed to attach to running process!");
-            self.run_debugger(target_pid);
+    fn attach_process(self, pid: Pid) {
+        self.attach_thread(pid)
+            .expect("Failed to attach to running process!");
+        println!("Attached to process {}", pid);
+
+        self.add_thread(pid, pid, ThreadState::Running);
+        self.attach_threads(pid);
+        self.run_debugger(pid);
+    }
+
+    /// Attaches to one thread, which then stops. PTRACE_SEIZE is used where
+    /// the kernel has it, stopping the thread with PTRACE_INTERRUPT, as the
+    /// SIGSTOP PTRACE_ATTACH stops it with would otherwise be handed to the
+    /// tracee when it's continued.
+    fn attach_thread(&self, tid: Pid) -> nix::Result<()> {
+        if ptrace::seize(tid, self.trace_options()).is_ok() {
+            return ptrace::interrupt(tid);
         }
+
+        // Taken as a Ctrl-C of ours when it turns up, and not passed on
+        INTERRUPT_SENT.store(true, Ordering::SeqCst);
+        ptrace::attach(tid)
+    }
+
+    /// Attaches to the rest of a process's threads, which ptrace deals with
+    /// one at a time. Threads started meanwhile by ones we hadn't got to yet
+    /// are picked up by going around again until there are no new ones.
+    fn attach_threads(&self, pid: Pid) {
+        loop {
+            let tids: Vec<Pid> = fs::read_dir(format!("/proc/{}/task", pid))
+                .map(|tasks| {
+                    tasks
+                        .filter_map(|task| task.ok()?.file_name().to_str()?.parse().ok())
+                        .map(Pid::from_raw)
+                        .filter(|tid| self.with_thread(*tid, |_| ()).is_none())
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            if tids.is_empty() {
+                break;
+            }
+
+            for tid in tids {
+                if self.attach_thread(tid).is_err() {
+                    continue;
+                }
+
+                let _ = waitpid(tid, Some(WaitPidFlag::__WALL));
+                let _ = ptrace::setoptions(tid, self.trace_options());
+                self.add_thread(pid, tid, ThreadState::Stopped);
+            }
+        }
+    }
+
+    /// What we ask ptrace to report besides signals
+    fn trace_options(&self) -> ptrace::Options {
+        ptrace::Options::PTRACE_O_TRACECLONE
+            | ptrace::Options::PTRACE_O_TRACEFORK
+            | ptrace::Options::PTRACE_O_TRACEVFORK
+            | ptrace::Options::PTRACE_O_TRACEVFORKDONE
+            | ptrace::Options::PTRACE_O_TRACEEXEC
+            | ptrace::Options::PTRACE_O_TRACESYSGOOD
     }
 
     fn run_target(self, prog_name: &CStr) {
@@ -63,41 +1430,382 @@ impl<'dwarf> TraceeDbg<'dwarf> {
         personality::set(tracee_persona | personality::Persona::ADDR_NO_RANDOMIZE)
             .expect("Critical Err: cannot set tracee process personality!");
 
+        // A process group of its own, so the terminal can be switched between
+        // it and the debugger
+        setpgid(Pid::from_raw(0), Pid::from_raw(0))
+            .expect("Critical Err: cannot give tracee its own process group!");
+
         ptrace::traceme().expect("Ptrace failed, cannot debug!");
-        let _ = execv(prog_name, &[] as &[&CStr]).expect("Failed to spawn process");
+        let Err(err_no) = execv(prog_name, &[] as &[&CStr]);
+        panic!("Failed to spawn process: {}", err_no);
     }
 
-    fn run_debugger(self, target_pid: Pid) {
+    fn run_debugger(mut self, target_pid: Pid) {
         println!("Entering debugging loop...");
 
         if self.symbols.is_none() {
             println!("WARNING: No debug symbols loaded!")
         }
 
+        TRACEE_PID.store(target_pid.as_raw(), Ordering::SeqCst);
+        self.current_thread.set(target_pid);
+
+        // SA_RESTART, so waitpid carries on waiting for the stop we caused
+        let interrupt = SigAction::new(
+            SigHandler::Handler(interrupt_tracee),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        unsafe { sigaction(Signal::SIGINT, &interrupt) }
+            .expect("Critical Err: cannot install the Ctrl-C handler!");
+
+        // Taking the terminal back from the tracee's group would otherwise
+        // stop us with SIGTTOU
+        let ignore = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
+        unsafe { sigaction(Signal::SIGTTOU, &ignore) }
+            .expect("Critical Err: cannot ignore SIGTTOU!");
+
+        let mut options_set = false;
+        let mut queued_stop = None;
+
         'await_process: loop {
-            let wait_status = waitpid(target_pid, None);
+            let (mut wait_status, mut fresh_stop, prompt_stop) = match queued_stop.take() {
+                Some(QueuedStop::Prompt(stop)) => (Ok(stop), false, None),
+                Some(QueuedStop::Event(status, stop)) => (Ok(status), true, Some(stop)),
+                None => {
+                    // Stops already waited on while stepping past a breakpoint
+                    // come before anything new
+                    let held = self.held_stops.borrow_mut().pop_front();
+
+                    let wait_status = held.map(Ok).unwrap_or_else(|| {
+                        let current = self.current_thread();
+                        TRACEE_PID.store(self.process_of(current).as_raw(), Ordering::SeqCst);
+                        TRACEE_TID.store(current.as_raw(), Ordering::SeqCst);
+                        TRACEE_RUNNING.store(true, Ordering::SeqCst);
+                        if let Some(pgrp) = self.tracee_pgrp.get() {
+                            give_terminal_to(pgrp);
+                        }
+                        let wait_status = waitpid(None, Some(WaitPidFlag::__WALL));
+                        TRACEE_RUNNING.store(false, Ordering::SeqCst);
+                        wait_status
+                    });
+                    (wait_status, true, None)
+                }
+            };
+
+            // Options can only be set on a stopped tracee, so wait for the first stop
+            if !options_set && matches!(wait_status, Ok(WaitStatus::Stopped(..))) {
+                options_set = ptrace::setoptions(target_pid, self.trace_options()).is_ok();
+            }
+
+            // Syscall stops are only reported for a catchpoint
+            let mut caught = match (fresh_stop, wait_status) {
+                (true, Ok(WaitStatus::PtraceSyscall(pid))) => match self.syscall_stop(pid) {
+                    Some(caught) => Some(caught),
+                    None => {
+                        if let Err(err_msg) = self.resume_as_before(pid) {
+                            eprintln!("Err: {}", err_msg);
+                        }
+
+                        queued_stop = prompt_stop.map(QueuedStop::Prompt);
+                        continue 'await_process;
+                    }
+                },
+                _ => None,
+            };
+
+            let waited_on = self.current_thread();
+            if let (true, Ok(WaitStatus::PtraceEvent(pid, _, libc::PTRACE_EVENT_EXEC))) =
+                (fresh_stop, wait_status)
+            {
+                let path = self.follow_exec(pid);
+
+                match self.catchpoint_id(|catchpoint| matches!(catchpoint.kind, CatchKind::Exec)) {
+                    Some(id) => {
+                        caught = Some(format!(
+                            "Catchpoint {} (exec'd {})",
+                            id,
+                            path.unwrap_or_default()
+                        ))
+                    }
+                    None => {
+                        if let Err(err_msg) = self.resume_as_before(pid) {
+                            eprintln!("Err: {}", err_msg);
+                        }
+
+                        queued_stop = prompt_stop.map(QueuedStop::Prompt);
+                        continue 'await_process;
+                    }
+                }
+            }
+
+            // A caught fork stops in whichever process is followed
+            if let (
+                true,
+                Ok(WaitStatus::PtraceEvent(
+                    pid,
+                    _,
+                    event @ (libc::PTRACE_EVENT_FORK | libc::PTRACE_EVENT_VFORK),
+                )),
+            ) = (fresh_stop, wait_status)
+            {
+                if let Some(id) =
+                    self.catchpoint_id(|catchpoint| matches!(catchpoint.kind, CatchKind::Fork))
+                {
+                    let vfork = event == libc::PTRACE_EVENT_VFORK;
+                    let child = ptrace::getevent(pid).unwrap_or_default();
+                    let followed = self.follow_fork(pid, vfork);
+
+                    wait_status = Ok(WaitStatus::PtraceEvent(followed, Signal::SIGTRAP, event));
+                    caught = Some(format!(
+                        "Catchpoint {} ({}forked process {})",
+                        id,
+                        if vfork { "v" } else { "" },
+                        child
+                    ));
+                }
+            }
+
+            if fresh_stop
+                && caught.is_none()
+                && wait_status.is_ok_and(|status| self.handle_thread_event(status))
+            {
+                queued_stop = prompt_stop
+                    .or_else(|| self.prompt_after_exit(waited_on))
+                    .map(QueuedStop::Prompt);
+                continue 'await_process;
+            }
+
+            // The thread that stopped, the others are about to be halted too
+            let tid = match wait_status {
+                Ok(status) => status.pid().unwrap_or(target_pid),
+                Err(_) => target_pid,
+            };
 
             'await_user: loop {
                 match wait_status {
-                    Ok(WaitStatus::Stopped(_, Signal::SIGTRAP))
-                    | Ok(WaitStatus::Stopped(_, Signal::SIGSTOP)) => {
+                    Ok(
+                        stop @ (WaitStatus::Stopped(..)
+                        | WaitStatus::PtraceEvent(..)
+                        | WaitStatus::PtraceSyscall(_)),
+                    ) => {
                         // First, check to see if the place where we stopped has an
                         // associated breakpoint. If PC == BPT_PC, then replace trap,
                         // rollback PC, and proceed.
 
-                        let regs =
-                            ptrace::getregs(target_pid).expect("FATAL: failed to send PTRACE_REGS");
+                        let regs = ptrace::getregs(tid).expect("FATAL: failed to send PTRACE_REGS");
+                        let trapped = stop == WaitStatus::Stopped(tid, Signal::SIGTRAP);
+                        let trap_addr = if fresh_stop && trapped {
+                            self.trap_hit_at(tid, regs.rip)
+                        } else {
+                            None
+                        };
 
-                        self.breakpoints
-                            .borrow()
-                            .get(&regs.rip)
-                            .map(|brkpt| brkpt.recover_from_trap());
+                        // Our own Ctrl-C SIGSTOP is a request for the prompt, not a
+                        // signal for the tracee, as are the terminal's SIGINT to a
+                        // tracee holding it and the stop a seized process is first
+                        // put in
+                        let attach_stop = fresh_stop
+                            && matches!(
+                                stop,
+                                WaitStatus::PtraceEvent(
+                                    _,
+                                    Signal::SIGTRAP,
+                                    libc::PTRACE_EVENT_STOP
+                                )
+                            );
+                        let interrupted = attach_stop
+                            || fresh_stop
+                                && stop == WaitStatus::Stopped(tid, Signal::SIGSTOP)
+                                && INTERRUPT_SENT.swap(false, Ordering::SeqCst)
+                            || fresh_stop
+                                && stop == WaitStatus::Stopped(tid, Signal::SIGINT)
+                                && self.tracee_pgrp.get().is_some()
+                                && ptrace::getsiginfo(tid)
+                                    .is_ok_and(|info| info.si_code == libc::SI_KERNEL);
 
-                        // dbg!(self.breakpoints.borrow());
+                        if fresh_stop {
+                            // A nexti's trap reached by another thread, or by a
+                            // deeper call, isn't where that step ends
+                            if let Some(addr) =
+                                trap_addr.filter(|addr| self.passes_step_over(tid, *addr, regs.rsp))
+                            {
+                                self.rewind_to_trap(tid, addr);
+
+                                match self.resume_as_before(tid) {
+                                    Ok(_) => {
+                                        queued_stop = prompt_stop.map(QueuedStop::Prompt);
+                                        continue 'await_process;
+                                    }
+                                    Err(err_msg) => eprintln!("Err: {}", err_msg),
+                                }
+                            }
+
+                            let signal = match stop {
+                                WaitStatus::Stopped(_, signal) if !trapped && !interrupted => {
+                                    Some(signal)
+                                }
+                                _ => None,
+                            };
+                            let policy = signal.map(|signal| self.signal_policy(signal));
+
+                            let pending =
+                                signal.filter(|_| policy.is_some_and(|policy| policy.pass));
+                            self.with_thread(tid, |thread| thread.pending_signal = pending);
+
+                            // A signal catchpoint stops regardless of how it's handled
+                            if let Some(signal) = signal {
+                                if let Some(id) = self
+                                    .catchpoint_id(|catchpoint| catchpoint.catches_signal(signal))
+                                {
+                                    caught = Some(format!("Catchpoint {} (signal {})", id, signal));
+                                }
+                            }
+
+                            // Signals we don't stop for go straight back to the tracee
+                            if let Some(policy) =
+                                policy.filter(|policy| !policy.stop && caught.is_none())
+                            {
+                                if policy.print {
+                                    println!("{}", describe_stop(stop, &regs));
+                                }
+
+                                match self.resume_as_before(tid) {
+                                    Ok(_) => {
+                                        queued_stop = prompt_stop.map(QueuedStop::Prompt);
+                                        continue 'await_process;
+                                    }
+                                    Err(err_msg) => eprintln!("Err: {}", err_msg),
+                                }
+                            }
+
+                            self.with_thread(tid, |thread| thread.state = ThreadState::Stopped);
+                            if !self.non_stop() {
+                                self.stop_other_threads(tid);
+                            }
+                            self.switch_to_thread(tid);
+                        }
+
+                        // Faults leave the process stopped for a post-mortem, the
+                        // signal only kills it once the user resumes
+                        let crashed = match stop {
+                            WaitStatus::Stopped(_, signal) if fresh_stop && is_crash(signal) => {
+                                Some(signal)
+                            }
+                            _ => None,
+                        };
+
+                        if interrupted {
+                            if !attach_stop {
+                                println!("Interrupted.");
+                            }
+                            self.pending_steps.set((0, false));
+                        } else if let (true, Some(caught)) = (fresh_stop, &caught) {
+                            println!("{}", caught);
+                            if let Some(signal) = crashed {
+                                print_crash_cause(tid, signal);
+                            }
+                            self.pending_steps.set((0, false));
+                        } else if let Some(signal) = crashed {
+                            print_crash_cause(tid, signal);
+                            self.pending_steps.set((0, false));
+                        } else if fresh_stop && !trapped {
+                            println!("{}", describe_stop(stop, &regs));
+
+                            // Anything other than a trap cuts a run of stepi short
+                            self.pending_steps.set((0, false));
+                        }
+
+                        // Breakpoints stay in, the thread's stepped past the one it
+                        // hit when it's resumed
+                        if let Some(addr) = trap_addr {
+                            self.rewind_to_trap(tid, addr);
+                        }
+
+                        // The temporary breakpoint a nexti planted goes away on any
+                        // stop, whether or not it was what we stopped on
+                        if let Some(step_over) = self.step_over.borrow_mut().take() {
+                            step_over.brkpt.deactivate();
+                        }
+
+                        if let Some(hit_addr) = trap_addr
+                            .filter(|addr| self.breakpoints.borrow().contains_key(&(addr + 1)))
+                        {
+                            self.pending_steps.set((0, false));
+
+                            let exception = self
+                                .catchpoints()
+                                .iter()
+                                .find(|catchpoint| catchpoint.traps_at(hit_addr))
+                                .map(|catchpoint| (catchpoint.id, catchpoint.describe()));
+
+                            // Both are reported where they share a trap
+                            if let Some((id, what)) = exception {
+                                println!(
+                                    "Catchpoint {} ({}) at {}",
+                                    id,
+                                    what,
+                                    self.describe_addr(self.current_thread(), hit_addr)
+                                );
+                            }
+                            let location_ids = self.brkpt_location_ids(hit_addr);
+                            if !location_ids.is_empty() {
+                                println!(
+                                    "Hit breakpoint {} at {}",
+                                    location_ids.join(", "),
+                                    self.describe_addr(self.current_thread(), hit_addr)
+                                );
+                            }
+                        }
+
+                        if let (count @ 1.., over_calls) = self.pending_steps.get() {
+                            self.pending_steps.set((count - 1, over_calls));
+
+                            match self.step_instruction(self.current_thread(), over_calls) {
+                                Ok(_) => continue 'await_process,
+                                Err(err_msg) => eprintln!("Err: {}", err_msg),
+                            }
+                        }
+
+                        if fresh_stop {
+                            self.reset_inline_skip(self.current_thread());
+                            fresh_stop = false;
+
+                            let next_insn = ExamineInsns {
+                                addr: AddrSpec::Register(String::from("rip")),
+                                count: 1,
+                            };
+                            let _ = next_insn.execute(self.current_thread(), &self);
+
+                            if let Some(signal) = crashed {
+                                println!("Backtrace:");
+                                if let Err(err_msg) =
+                                    Backtrace.execute(self.current_thread(), &self)
+                                {
+                                    eprintln!("Err: {}", err_msg);
+                                }
+                                println!(
+                                    "The process is stopped for inspection; resuming delivers {}, \"signal 0\" discards it and \"q\" kills the process.",
+                                    signal
+                                );
+                            }
+                        }
+
+                        // Threads left running in non-stop mode may have stopped
+                        // meanwhile, if so that's reported before prompting again
+                        if self.non_stop() {
+                            match waitpid(None, Some(WaitPidFlag::WNOHANG | WaitPidFlag::__WALL)) {
+                                Ok(WaitStatus::StillAlive) | Err(_) => {}
+                                Ok(status) => {
+                                    queued_stop = Some(QueuedStop::Event(status, stop));
+                                    continue 'await_process;
+                                }
+                            }
+                        }
 
                         match self
                             .prompt_user_cmd()
-                            .and_then(|cmd| cmd.execute(target_pid, self.position_ind_p))
+                            .and_then(|cmd| cmd.execute(self.current_thread(), &self))
                         {
                             Ok(TargetStat::AwaitingCommand) => {
                                 continue 'await_user;
@@ -107,20 +1815,76 @@ impl<'dwarf> TraceeDbg<'dwarf> {
                                 continue 'await_process;
                             }
 
-                            Ok(TargetStat::Killed) => {
-                                println!("Process killed, exiting...");
-                                break 'await_process;
-                            }
+                            Ok(TargetStat::Killed) => {
+                                println!("Process killed, exiting...");
+                                break 'await_process;
+                            }
+
+                            Ok(TargetStat::Detached) => {
+                                println!("Process detached, exiting...");
+                                break 'await_process;
+                            }
+
+                            Ok(TargetStat::BreakpointAdded(spec, brkptrecs)) => {
+                                let mut group = BrkptGroup {
+                                    id: self.next_brkpt_id(),
+                                    spec,
+                                    locations: Vec::new(),
+                                };
+
+                                for brkptrec in brkptrecs {
+                                    let brkpt_addr = brkptrec.pc_addr as u64;
+
+                                    // Shares the trap that's already there
+                                    if self.breakpoints.borrow().contains_key(&(brkpt_addr + 1)) {
+                                        let location_ids = self.brkpt_location_ids(brkpt_addr);
+                                        if !location_ids.is_empty() {
+                                            println!(
+                                                "Note: breakpoint {} also set at pc {:#x}",
+                                                location_ids.join(", "),
+                                                brkpt_addr
+                                            );
+                                        }
+                                        group.locations.push(brkpt_addr);
+                                        continue;
+                                    }
+
+                                    brkptrec.activate();
+                                    group.locations.push(brkpt_addr);
+                                    self.breakpoints.borrow_mut().insert(
+                                        (brkptrec.pc_addr.wrapping_add(1)) as u64,
+                                        brkptrec,
+                                    );
+                                }
+
+                                match group.locations.as_slice() {
+                                    [] => {}
+                                    [addr] => println!(
+                                        "Breakpoint {} at {}",
+                                        group.id,
+                                        self.describe_addr(self.current_thread(), *addr)
+                                    ),
+                                    addrs => {
+                                        println!(
+                                            "Breakpoint {} at {} ({} locations)",
+                                            group.id,
+                                            group.spec,
+                                            addrs.len()
+                                        );
+                                        for (idx, addr) in addrs.iter().enumerate() {
+                                            println!(
+                                                "  {}.{}: {}",
+                                                group.id,
+                                                idx + 1,
+                                                self.describe_addr(self.current_thread(), *addr)
+                                            );
+                                        }
+                                    }
+                                }
 
-                            Ok(TargetStat::BreakpointAdded(brkptrec)) => {
-                                println!(
-                                    "Breakpoint added, activating: {:#x}",
-                                    brkptrec.pc_addr as u64
-                                );
-                                brkptrec.activate();
-                                self.breakpoints
-                                    .borrow_mut()
-                                    .insert((brkptrec.pc_addr.wrapping_add(1)) as u64, brkptrec);
+                                if !group.locations.is_empty() {
+                                    self.brkpt_groups.borrow_mut().push(group);
+                                }
 
                                 continue 'await_user;
                             }
@@ -132,23 +1896,32 @@ impl<'dwarf> TraceeDbg<'dwarf> {
                         }
                     }
 
-                    Ok(WaitStatus::Stopped(_, Signal::SIGSEGV)) => {
-                        println!("Target process received SIGSEGV, segfaulted!");
+                    Ok(WaitStatus::Exited(_, exit_code)) => {
+                        println!(
+                            "The target program finished execution with exit code {}.",
+                            exit_code
+                        );
                         break 'await_process;
                     }
 
-                    Ok(WaitStatus::Exited(_, ..)) => {
-                        println!("The target program finished execution.");
+                    Ok(WaitStatus::Signaled(_, signal, core_dumped)) => {
+                        println!(
+                            "The target program was terminated by {}{}.",
+                            signal,
+                            if core_dumped { " (core dumped)" } else { "" }
+                        );
                         break 'await_process;
                     }
 
-                    Ok(_unhandled) => {
-                        dbg!(_unhandled);
-                        todo!();
+                    // Neither leaves the tracee stopped, so there's nothing to do yet
+                    Ok(WaitStatus::Continued(_)) | Ok(WaitStatus::StillAlive) => {
+                        continue 'await_process;
                     }
 
-                    Err(_) => {
-                        panic!("Critical failure: failed to wait for target program!");
+                    Err(err_no) => {
+                        eprintln!("ERRNO {}", err_no);
+                        println!("Lost track of the target program, exiting...");
+                        break 'await_process;
                     }
                 }
             }
@@ -156,32 +1929,99 @@ impl<'dwarf> TraceeDbg<'dwarf> {
     }
 
     fn prompt_user_cmd(&self) -> Result<Box<dyn Execute>, &'static str> {
+        if self.tracee_pgrp.get().is_some() {
+            give_terminal_to(getpgrp());
+        }
+
         print!("> ");
         stdout().flush().unwrap();
 
         let mut user_input = String::new();
 
-        while let Err(_) = stdin().read_line(&mut user_input) {
-            eprintln!("Err: Failed to read user input, please enter a proper command!");
-            user_input.clear();
+        loop {
+            match stdin().read_line(&mut user_input) {
+                // End of input, there's nobody left to take commands from
+                Ok(0) => return Ok(Box::new(Quit)),
+                Ok(_) => break,
+                Err(_) => {
+                    eprintln!("Err: Failed to read user input, please enter a proper command!");
+                    user_input.clear();
+                }
+            }
         }
 
         let mut term_iter = user_input.split_whitespace();
 
-        let (command, mut args_iter) = (term_iter.nth(0).unwrap(), term_iter);
+        let (command, mut args_iter) = (
+            term_iter
+                .next()
+                .ok_or("Please enter a command, or help for a list!")?,
+            term_iter,
+        );
 
         match command {
             // Commands with no operands
-            "reg" | "registers" => Ok(Box::new(ViewRegisters)),
+            "reg" | "registers" => Ok(Box::new(ViewRegisters { names: Vec::new() })),
             "s" | "step" => Ok(Box::new(Step)),
-            "c" | "continue" => Ok(Box::new(Continue)),
+            "c" | "continue" => Ok(Box::new(Continue {
+                all: args_iter.next() == Some("-a"),
+            })),
+            "interrupt" => Ok(Box::new(Interrupt {
+                all: args_iter.next() == Some("-a"),
+            })),
             "q" | "quit" => Ok(Box::new(Quit)),
+            "detach" => Ok(Box::new(Detach)),
             "h" | "help" => Ok(Box::new(HelpMe)),
+            "bt" | "backtrace" => Ok(Box::new(Backtrace)),
+            "disas" | "disassemble" => {
+                let args: Vec<&str> = args_iter.collect();
+
+                // Ranges go as "start end", "start,end" or "start,+length"
+                let range = match args.join(" ").split([',', ' ']).collect::<Vec<&str>>()[..] {
+                    [""] => DisasRange::CurrentFunction,
+                    [start, end] => {
+                        let (start, end) = parse_addr_range(start, end)?;
+                        DisasRange::Addrs(start, end)
+                    }
+                    [arg] if arg.starts_with("0x") => DisasRange::Containing(parse_hex_addr(arg)?),
+                    [arg] => DisasRange::Function(arg.to_string()),
+                    _ => return Err("Failed to parse, please supply a function or address range!"),
+                };
+
+                Ok(Box::new(Disassemble { range }))
+            }
+
+            // Commands with an optional operand
+            "si" | "stepi" | "ni" | "nexti" => {
+                let count = match args_iter.next() {
+                    Some(arg) => arg
+                        .parse::<usize>()
+                        .map_err(|_| "Failed to parse the number of instructions to step!")?,
+                    None => 1,
+                };
+
+                Ok(Box::new(StepInstruction {
+                    count,
+                    over_calls: matches!(command, "ni" | "nexti"),
+                }))
+            }
+
+            "thread" => {
+                let id = args_iter
+                    .next()
+                    .map(|arg| {
+                        arg.parse::<usize>()
+                            .map_err(|_| "Failed to parse, please supply a thread number!")
+                    })
+                    .transpose()?;
+
+                Ok(Box::new(SelectThread { id }))
+            }
 
             // Commands with a single operand
             "r" | "read" => {
                 let result = args_iter
-                    .nth(0)
+                    .next()
                     .ok_or("Missing the address to read from")
                     .and_then(|arg| {
                         usize::from_str_radix(arg, 16)
@@ -197,6 +2037,60 @@ impl<'dwarf> TraceeDbg<'dwarf> {
                 }
             }
 
+            cmd if cmd == "x" || cmd.starts_with("x/") => {
+                let fmt = cmd.strip_prefix("x/").unwrap_or("");
+                let (count, letters) =
+                    fmt.split_at(fmt.find(|c: char| !c.is_ascii_digit()).unwrap_or(fmt.len()));
+                let count = if count.is_empty() {
+                    1
+                } else {
+                    count
+                        .parse()
+                        .map_err(|_| "Failed to parse the number of units to examine!")?
+                };
+
+                let addr = args_iter
+                    .next()
+                    .ok_or("Missing the address to examine")
+                    .and_then(parse_addr_spec)?;
+
+                // Format and unit letters may come in either order, as in gdb
+                let (mut format, mut unit) = (None, None);
+                for letter in letters.chars() {
+                    match letter {
+                        'b' => unit = Some(1),
+                        'h' => unit = Some(2),
+                        'w' => unit = Some(4),
+                        'g' => unit = Some(8),
+                        'i' => return Ok(Box::new(ExamineInsns { addr, count })),
+                        _ => {
+                            format = Some(
+                                MemFormat::from_letter(letter).ok_or("Unrecognized x format!")?,
+                            )
+                        }
+                    }
+                }
+
+                let format = format.unwrap_or(MemFormat::Hex);
+                let unit = match format {
+                    MemFormat::Char | MemFormat::Str => 1,
+                    MemFormat::Addr => 8,
+                    MemFormat::Float => unit.unwrap_or(8),
+                    _ => unit.unwrap_or(4),
+                };
+
+                if format == MemFormat::Float && unit < 4 {
+                    return Err("Floats can only be examined as w or g units!");
+                }
+
+                Ok(Box::new(ExamineMemory {
+                    addr,
+                    count,
+                    format,
+                    unit,
+                }))
+            }
+
             // Commands with two operands
             "w" | "write" => {
                 let mut res = args_iter.take(2).map(|arg| usize::from_str_radix(arg, 16));
@@ -211,29 +2105,422 @@ impl<'dwarf> TraceeDbg<'dwarf> {
                 }
             }
             "b" | "breakpoint" => {
-                if let Some(ref symref) = self.symbols {
-                    let res = args_iter
+                let arg = args_iter
+                    .next()
+                    .ok_or("Insufficient arguments for command!")?;
+
+                self.resolve_location(arg)
+                    .map(|(spec, addrs)| Box::new(Breakpoint { spec, addrs }) as Box<dyn Execute>)
+            }
+
+            "i" | "info" => match args_iter.next() {
+                Some("r") | Some("reg") | Some("registers") => {
+                    let names: Option<Vec<String>> = args_iter.map(normalize_reg_name).collect();
+
+                    match names {
+                        Some(names) => Ok(Box::new(ViewRegisters { names })),
+                        None => Err("Unknown register name!"),
+                    }
+                }
+
+                Some("float") => Ok(Box::new(InfoFloat)),
+
+                Some("vector") => {
+                    let regs: Option<Vec<(VectorKind, usize)>> = args_iter
+                        .map(|name| match parse_fp_target(name) {
+                            Some(FpTarget::Vector(kind, idx)) => Some((kind, idx)),
+                            _ => None,
+                        })
+                        .collect();
+
+                    match regs {
+                        Some(regs) => Ok(Box::new(InfoVector { regs })),
+                        None => Err("Unknown vector register name!"),
+                    }
+                }
+
+                Some("symbol") => {
+                    let result = args_iter
                         .next()
-                        .as_deref()
-                        .ok_or("Insufficient arguments for command!")
-                        .and_then(|arg| parse_file_and_lineno(arg))
-                        .and_then(|(fname, lno)| {
-                            src_line_to_addr(symref.borrow(), fname, lno)
-                                .map_err(|_| "Failed to resolve address!")
+                        .ok_or("Missing the address to look up")
+                        .and_then(|arg| {
+                            u64::from_str_radix(arg.trim_start_matches("0x"), 16)
+                                .map_err(|_| "Failed to parse: please supply hex value!")
                         });
 
-                    match res {
-                        Ok(addr) => Ok(Box::new(Breakpoint(addr))),
-                        Err(msg) => Err(msg),
+                    match result {
+                        Ok(addr) => Ok(Box::new(InfoSymbol { addr })),
+                        Err(str) => Err(str),
+                    }
+                }
+
+                Some("b") | Some("break") | Some("breakpoints") => Ok(Box::new(InfoBreakpoints)),
+
+                Some("threads") => Ok(Box::new(InfoThreads)),
+
+                Some("signals") | Some("handle") => {
+                    let signal = args_iter
+                        .next()
+                        .map(|name| parse_signal(name).ok_or("Unknown signal name!"))
+                        .transpose()?;
+
+                    Ok(Box::new(InfoSignals { signal }))
+                }
+
+                Some("proc") => match args_iter.next() {
+                    Some("map") | Some("mappings") => {
+                        let addr = args_iter.next().map(parse_hex_addr).transpose()?;
+
+                        Ok(Box::new(InfoProcMappings { addr }))
+                    }
+                    _ => Err("Unrecognized info proc subcommand!"),
+                },
+
+                _ => Err("Unrecognized info subcommand!"),
+            },
+
+            "catch" => match args_iter.next() {
+                Some("syscall") => {
+                    let syscalls: Option<Vec<u64>> = args_iter.map(parse_syscall).collect();
+
+                    match syscalls {
+                        Some(syscalls) => Ok(Box::new(CatchSyscall { syscalls })),
+                        None => Err("Unknown syscall name or number!"),
+                    }
+                }
+                Some("fork") => Ok(Box::new(CatchFork)),
+                Some("exec") => Ok(Box::new(CatchExec)),
+                Some("signal") => {
+                    let signals: Option<Vec<Signal>> = match args_iter.clone().next() {
+                        Some("all") => Some(Signal::iterator().collect()),
+                        _ => args_iter.map(parse_signal).collect(),
+                    };
+
+                    match signals {
+                        Some(signals) => Ok(Box::new(CatchSignal { signals })),
+                        None => Err("Unknown signal!"),
+                    }
+                }
+                Some("throw") => Ok(Box::new(CatchException { thrown: true })),
+                Some("catch") => Ok(Box::new(CatchException { thrown: false })),
+                _ => Err("Unrecognized catch subcommand!"),
+            },
+
+            "trace" => match (args_iter.next(), args_iter.next()) {
+                (Some("syscalls"), None | Some("on")) => {
+                    Ok(Box::new(TraceSyscalls { enabled: true }))
+                }
+                (Some("syscalls"), Some("off")) => Ok(Box::new(TraceSyscalls { enabled: false })),
+                _ => Err("Failed to parse, please use trace syscalls [on | off]"),
+            },
+
+            "set" if user_input.split_whitespace().nth(1) == Some("follow-fork-mode") => {
+                let child = match args_iter.nth(1) {
+                    Some("parent") => false,
+                    Some("child") => true,
+                    _ => {
+                        return Err("Failed to parse, please use set follow-fork-mode parent/child")
                     }
+                };
+
+                Ok(Box::new(SetFollowForkMode { child }))
+            }
+
+            "set" if user_input.split_whitespace().nth(1) == Some("detach-on-fork") => {
+                let enabled = match args_iter.nth(1) {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => return Err("Failed to parse, please use set detach-on-fork on/off"),
+                };
+
+                Ok(Box::new(SetDetachOnFork { enabled }))
+            }
+
+            "set" if user_input.split_whitespace().nth(1) == Some("non-stop") => {
+                let enabled = match args_iter.nth(1) {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => return Err("Failed to parse, please use set non-stop on/off"),
+                };
+
+                Ok(Box::new(SetNonStop { enabled }))
+            }
+
+            "set" => {
+                // Allow both "set $rax = 1" and "set $rax=1"
+                let assignment: String = args_iter.collect::<Vec<&str>>().join(" ");
+                let (lhs, rhs) = assignment
+                    .split_once('=')
+                    .ok_or("Failed to parse, please supply in format of set $reg = value")?;
+
+                let (lhs, rhs) = (lhs.trim(), rhs.trim());
+
+                if let Some(name) = lhs.strip_prefix('$').and_then(normalize_reg_name) {
+                    let val = parse_value(rhs)?;
+
+                    Ok(Box::new(SetRegister { name, val }))
+                } else if let Some(target) = lhs.strip_prefix('$').and_then(parse_fp_target) {
+                    let bytes = encode_fp_value(&target, rhs)?;
+
+                    Ok(Box::new(SetFpRegister { target, bytes }))
+                } else if lhs.starts_with('$') {
+                    Err("Unknown register name!")
+                } else if let Some((ctype, addr)) =
+                    lhs.strip_prefix('{').and_then(|lhs| lhs.split_once('}'))
+                {
+                    Ok(Box::new(WriteMemory {
+                        addr: parse_addr_spec(addr.trim())?,
+                        bytes: encode_typed_value(ctype.trim(), rhs)?,
+                    }))
+                } else {
+                    Err("Unrecognized set command!")
+                }
+            }
+
+            "find" => {
+                // Patterns may hold quoted whitespace, so work off the raw line
+                let rest = user_input.trim().trim_start_matches(command).trim_start();
+
+                let first = rest.split_whitespace().next().unwrap_or("");
+                let is_pattern = rest.starts_with('"')
+                    || rest.starts_with("u\"")
+                    || ["/x", "/b", "/h", "/w", "/g"].contains(&first);
+
+                let (range, pattern) = if is_pattern {
+                    (MemRange::Readable, rest)
+                } else {
+                    let (range, pattern) = rest
+                        .split_once(char::is_whitespace)
+                        .ok_or("Missing the pattern to search for")?;
+                    (parse_mem_range(range)?, pattern.trim_start())
+                };
+
+                let pattern = parse_search_pattern(pattern)?;
+                if pattern.is_empty() {
+                    return Err("Cannot search for an empty pattern!");
+                }
+
+                Ok(Box::new(FindMemory { range, pattern }))
+            }
+
+            "dump" => {
+                if args_iter.next() != Some("memory") {
+                    return Err("Unrecognized dump subcommand, please use dump memory!");
+                }
+
+                let path = args_iter.next().ok_or("Missing the file to dump to")?;
+                let range = match (args_iter.next(), args_iter.next()) {
+                    (Some(start), Some(end)) => parse_mem_range(&format!("{},{}", start, end))?,
+                    (Some(range), None) => parse_mem_range(range)?,
+                    _ => return Err("Missing the memory range to dump"),
+                };
+
+                Ok(Box::new(DumpMemory {
+                    path: path.to_string(),
+                    range,
+                }))
+            }
+
+            "restore" => {
+                let path = args_iter.next().ok_or("Missing the file to restore from")?;
+                let addr = args_iter
+                    .next()
+                    .ok_or("Missing the address to restore to")
+                    .and_then(parse_addr_spec)?;
+
+                Ok(Box::new(RestoreMemory {
+                    path: path.to_string(),
+                    addr,
+                }))
+            }
+
+            "snapshot" => match (args_iter.next(), args_iter.next()) {
+                (Some("save"), Some(name)) => {
+                    let range = match args_iter.next() {
+                        Some(range) => parse_mem_range(range)?,
+                        None => MemRange::Writable,
+                    };
+
+                    Ok(Box::new(SaveSnapshot {
+                        name: name.to_string(),
+                        range,
+                    }))
+                }
+                (Some("diff"), Some(name)) => Ok(Box::new(DiffSnapshot {
+                    name: name.to_string(),
+                })),
+                (Some("delete"), Some(name)) => Ok(Box::new(DeleteSnapshot {
+                    name: name.to_string(),
+                })),
+                (Some("list"), None) => Ok(Box::new(ListSnapshots)),
+                _ => Err(
+                    "Failed to parse, please use snapshot save/diff/delete <name> or snapshot list",
+                ),
+            },
+
+            "handle" => {
+                let signal = args_iter.next().ok_or("Missing the signal to handle")?;
+                let actions: Vec<String> = args_iter.map(String::from).collect();
+
+                let signals = if signal == "all" {
+                    Signal::iterator()
+                        .filter(|signal| !matches!(signal, Signal::SIGTRAP | Signal::SIGINT))
+                        .collect()
                 } else {
-                    Err("Cannot resolve source lines without debug symbols!")
+                    vec![parse_signal(signal).ok_or("Unknown signal name!")?]
+                };
+
+                Ok(Box::new(HandleSignal { signals, actions }))
+            }
+
+            "signal" => {
+                let signal = args_iter.next().ok_or("Missing the signal to send")?;
+
+                // "signal 0" resumes without any signal, dropping a pending one
+                let signal = match signal {
+                    "0" => None,
+                    name => Some(parse_signal(name).ok_or("Unknown signal name!")?),
+                };
+
+                Ok(Box::new(SendSignal { signal }))
+            }
+
+            "write-bytes" => {
+                let addr = args_iter
+                    .next()
+                    .ok_or("Missing the address to write to")
+                    .and_then(parse_addr_spec)?;
+
+                let bytes = args_iter
+                    .map(|arg| u8::from_str_radix(arg.trim_start_matches("0x"), 16))
+                    .collect::<Result<Vec<u8>, _>>()
+                    .map_err(|_| "Failed to parse: please supply hex bytes!")?;
+
+                if bytes.is_empty() {
+                    return Err("Missing the bytes to write");
                 }
+
+                Ok(Box::new(WriteBytes { addr, bytes }))
+            }
+
+            "write-string" => {
+                let addr = args_iter
+                    .next()
+                    .ok_or("Missing the address to write to")
+                    .and_then(parse_addr_spec)?;
+
+                // Whitespace inside the quotes matters, so go back to the raw line
+                let text = user_input
+                    .split_once('"')
+                    .and_then(|(_, rest)| rest.trim_end().strip_suffix('"'))
+                    .ok_or("Failed to parse, please supply a double quoted string!")
+                    .and_then(parse_string_literal)?;
+
+                Ok(Box::new(WriteString { addr, text }))
             }
 
             _ => Err("Could not recognize command!"),
         }
     }
+
+    /// Resolves a function name to its out-of-line copy, if there is one,
+    /// and to every place it was inlined. A name shared by several functions
+    /// (C's main and a Rust crate's, say) resolves to all of them, as one
+    /// breakpoint with a location for each.
+    fn resolve_function(&self, name: &str) -> Result<Vec<u64>, &'static str> {
+        if self.elf_symbols.is_none() && self.symbols.is_none() {
+            return Err("Cannot resolve function names without a symbol table!");
+        }
+
+        let mut addrs: Vec<u64> = self
+            .elf_symbols()
+            .map(|symbols| symbol_name_to_addrs(symbols, name))
+            .unwrap_or_default()
+            .into_iter()
+            // Out-of-line copies have a prologue to get past, inlined ones don't
+            .map(|sym| {
+                self.symbols()
+                    .and_then(|dwarf| skip_prologue(dwarf, sym.addr).ok())
+                    .unwrap_or(sym.addr)
+            })
+            .collect();
+
+        if let Some(dwarf) = self.symbols() {
+            let inlined = inlined_instances_of(dwarf, name).unwrap_or_default();
+            addrs.extend(inlined.into_iter().map(|(_, addr)| addr));
+        }
+
+        addrs.sort();
+        addrs.dedup();
+
+        if addrs.is_empty() {
+            return Err("Failed to find a function by that name!");
+        }
+
+        Ok(addrs)
+    }
+}
+
+/// Explains why the tracee stopped, for anything other than a plain trap
+fn describe_stop(stop: WaitStatus, regs: &user_regs_struct) -> String {
+    match stop {
+        WaitStatus::Stopped(_, signal) => format!("Target process received {}", signal),
+
+        WaitStatus::PtraceEvent(_, _, event) => {
+            let event = match event {
+                libc::PTRACE_EVENT_FORK => "fork",
+                libc::PTRACE_EVENT_VFORK => "vfork",
+                libc::PTRACE_EVENT_CLONE => "clone",
+                libc::PTRACE_EVENT_EXEC => "exec",
+                libc::PTRACE_EVENT_VFORK_DONE => "vfork done",
+                libc::PTRACE_EVENT_EXIT => "exit",
+                libc::PTRACE_EVENT_SECCOMP => "seccomp",
+                libc::PTRACE_EVENT_STOP => "group stop",
+                _ => "unknown",
+            };
+            format!("Target process stopped at ptrace event: {}", event)
+        }
+
+        WaitStatus::PtraceSyscall(_) => match syscall_name(regs.orig_rax) {
+            Some(name) => format!("Target process stopped at syscall {}", name),
+            None => format!("Target process stopped at syscall {}", regs.orig_rax),
+        },
+
+        _ => format!("Target process stopped: {:?}", stop),
+    }
+}
+
+/// Names a fatal signal and, from its siginfo, why it was sent and which
+/// address faulted
+fn print_crash_cause(pid: Pid, signal: Signal) {
+    let siginfo = match ptrace::getsiginfo(pid) {
+        Ok(siginfo) => siginfo,
+        Err(_) => {
+            println!("Target process received {}, crashed!", signal);
+            return;
+        }
+    };
+
+    println!(
+        "Target process received {}, crashed! ({})",
+        signal,
+        describe_si_code(signal, siginfo.si_code)
+    );
+
+    if has_fault_addr(signal, siginfo.si_code) {
+        println!("Faulting address: {:#x}", unsafe { siginfo.si_addr() }
+            as u64);
+    }
+}
+
+/// Tells "file.c:10" apart from function paths like "mycrate::parser::parse"
+fn is_file_and_lineno(string: &str) -> bool {
+    match string.rsplit_once(':') {
+        Some((file, lineno)) => {
+            !file.is_empty() && !file.ends_with(':') && lineno.parse::<u64>().is_ok()
+        }
+        None => false,
+    }
 }
 
 fn parse_file_and_lineno(string: &str) -> Result<(&str, u64), &'static str> {
@@ -244,16 +2531,234 @@ fn parse_file_and_lineno(string: &str) -> Result<(&str, u64), &'static str> {
     }
 
     if let Ok(lineno) = vec.index(1).parse::<u64>() {
-        return Ok((vec[0], lineno));
+        Ok((vec[0], lineno))
+    } else {
+        Err("Failed to parse a line number from supplied argument!")
+    }
+}
+
+/// Parses an address, with or without a "0x" prefix
+fn parse_hex_addr(string: &str) -> Result<u64, &'static str> {
+    u64::from_str_radix(string.trim_start_matches("0x"), 16)
+        .map_err(|_| "Failed to parse: please supply hex value!")
+}
+
+/// Parses either a hex address or a register holding one, e.g. "$pc"
+fn parse_addr_spec(string: &str) -> Result<AddrSpec, &'static str> {
+    match string.strip_prefix('$') {
+        Some("pc") => Ok(AddrSpec::Register(String::from("rip"))),
+        Some(reg) => normalize_reg_name(reg)
+            .map(AddrSpec::Register)
+            .ok_or("Unknown register name!"),
+        None => parse_hex_addr(string).map(AddrSpec::Addr),
+    }
+}
+
+/// Parses "0x"-prefixed hex or (possibly negative) decimal integers
+fn parse_value(string: &str) -> Result<u64, &'static str> {
+    let parsed = if let Some(hex) = string.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(neg) = string.strip_prefix('-') {
+        neg.parse::<u64>().ok().map(|val| val.wrapping_neg())
     } else {
-        return Err("Failed to parse a line number from supplied argument!");
+        string.parse::<u64>().ok()
+    };
+
+    parsed.ok_or("Failed to parse value, please supply a decimal or 0x-prefixed hex integer!")
+}
+
+/// Parses a memory range given as "start,end", "start,+length", or a
+/// mapping name such as "[heap]" or the file a mapping is backed by
+fn parse_mem_range(string: &str) -> Result<MemRange, &'static str> {
+    match string.split_once(',') {
+        Some((start, end)) => {
+            let (start, end) = parse_addr_range(start, end)?;
+            Ok(MemRange::Addrs(start, end))
+        }
+        None => Ok(MemRange::Mapping(string.to_string())),
+    }
+}
+
+/// Parses the two halves of "start,end" or "start,+length"
+fn parse_addr_range(start: &str, end: &str) -> Result<(u64, u64), &'static str> {
+    let start = parse_hex_addr(start.trim())?;
+    let end = match end.trim().strip_prefix('+') {
+        Some(len) if len.starts_with('-') => return Err("Range length cannot be negative!"),
+        Some(len) => start
+            .checked_add(parse_value(len)?)
+            .ok_or("Range end overflows the address space!")?,
+        None => parse_hex_addr(end.trim())?,
+    };
+
+    if end <= start {
+        return Err("Empty address range!");
+    }
+
+    Ok((start, end))
+}
+
+/// Encodes what find looks for: "text" as ASCII, u"text" as UTF-16, /x
+/// followed by hex bytes, or /b, /h, /w, /g followed by an integer value
+fn parse_search_pattern(string: &str) -> Result<Vec<u8>, &'static str> {
+    let quoted = |string: &str| {
+        string
+            .strip_prefix('"')
+            .and_then(|body| body.strip_suffix('"'))
+            .ok_or("Failed to parse, please supply a double quoted string!")
+            .and_then(parse_string_literal)
+    };
+
+    if let Some(text) = string.strip_prefix('u') {
+        let text = String::from_utf8(quoted(text)?).map_err(|_| "Strings must be UTF-8!")?;
+        return Ok(text.encode_utf16().flat_map(u16::to_le_bytes).collect());
+    }
+
+    if string.starts_with('"') {
+        return quoted(string);
+    }
+
+    let (flag, value) = string
+        .split_once(char::is_whitespace)
+        .ok_or("Missing the value to search for")?;
+
+    let width = match flag {
+        "/x" => {
+            return value
+                .split_whitespace()
+                .map(|byte| u8::from_str_radix(byte.trim_start_matches("0x"), 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| "Failed to parse: please supply hex bytes!");
+        }
+        "/b" => 1,
+        "/h" => 2,
+        "/w" => 4,
+        "/g" => 8,
+        _ => return Err("Unrecognized pattern type, please use /x, /b, /h, /w or /g!"),
+    };
+
+    Ok(parse_value(value.trim())?.to_le_bytes()[..width].to_vec())
+}
+
+/// Encodes a value as the C (or Rust) type it's being stored as
+fn encode_typed_value(ctype: &str, string: &str) -> Result<Vec<u8>, &'static str> {
+    let width = match ctype {
+        "float" | "f32" => {
+            let val = string
+                .parse::<f32>()
+                .map_err(|_| "Failed to parse a floating-point value!")?;
+            return Ok(val.to_le_bytes().to_vec());
+        }
+        "double" | "f64" => {
+            let val = string
+                .parse::<f64>()
+                .map_err(|_| "Failed to parse a floating-point value!")?;
+            return Ok(val.to_le_bytes().to_vec());
+        }
+
+        "char" | "unsigned char" | "signed char" | "int8_t" | "uint8_t" | "i8" | "u8" => 1,
+        "short" | "unsigned short" | "int16_t" | "uint16_t" | "i16" | "u16" => 2,
+        "int" | "unsigned int" | "unsigned" | "int32_t" | "uint32_t" | "i32" | "u32" => 4,
+        "long" | "unsigned long" | "long long" | "unsigned long long" | "int64_t" | "uint64_t"
+        | "size_t" | "i64" | "u64" | "usize" | "isize" => 8,
+        ctype if ctype.ends_with('*') => 8,
+
+        _ => return Err("Unknown type, please use a C integer or floating-point type!"),
+    };
+
+    // Character literals are accepted for char-sized stores, e.g. 'A'
+    let val = match string.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')) {
+        Some(chr) if width == 1 => match parse_string_literal(chr)?[..] {
+            [byte] => byte as u64,
+            _ => return Err("Character literals must be a single byte!"),
+        },
+        _ => parse_value(string)?,
+    };
+
+    Ok(val.to_le_bytes()[..width].to_vec())
+}
+
+/// Unescapes the body of a string literal, handling the usual \n, \t, \0,
+/// \\, \" and \xHH escapes
+fn parse_string_literal(string: &str) -> Result<Vec<u8>, &'static str> {
+    let mut bytes = Vec::new();
+    let mut chars = string.chars();
+
+    while let Some(chr) = chars.next() {
+        if chr != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(chr.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+
+        let byte = match chars.next() {
+            Some('n') => b'\n',
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('"') => b'"',
+            Some('\'') => b'\'',
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&hex, 16).map_err(|_| "Bad \\x escape in string!")?
+            }
+            _ => return Err("Unrecognized escape in string!"),
+        };
+        bytes.push(byte);
+    }
+
+    Ok(bytes)
+}
+
+/// Encodes a value for an x87 register (as a float), a whole vector register
+/// (as raw hex) or a single lane of one (as a float or integer per its view)
+fn encode_fp_value(target: &FpTarget, string: &str) -> Result<Vec<u8>, &'static str> {
+    match *target {
+        FpTarget::St(_) => string
+            .parse::<f64>()
+            .map(|val| f64_to_f80(val).to_vec())
+            .map_err(|_| "Failed to parse a floating-point value!"),
+
+        FpTarget::Vector(kind, _) => {
+            let hex = string
+                .strip_prefix("0x")
+                .ok_or("Whole vector registers take a 0x-prefixed hex value!")?;
+
+            let width = vector_width(kind);
+            if hex.len() > width * 2 {
+                return Err("Value is wider than the register!");
+            }
+
+            // Zero extend to the register width, then store little endian
+            let padded = format!("{:0>1$}", hex, width * 2);
+            (0..width)
+                .rev()
+                .map(|idx| u8::from_str_radix(&padded[idx * 2..idx * 2 + 2], 16))
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| "Failed to parse hex value!")
+        }
+
+        FpTarget::Lane(_, _, view, _) => match view {
+            LaneView::F32 => string
+                .parse::<f32>()
+                .map(|val| val.to_le_bytes().to_vec())
+                .map_err(|_| "Failed to parse a floating-point value!"),
+            LaneView::F64 => string
+                .parse::<f64>()
+                .map(|val| val.to_le_bytes().to_vec())
+                .map_err(|_| "Failed to parse a floating-point value!"),
+            _ => parse_value(string).map(|val| val.to_le_bytes()[..view.width()].to_vec()),
+        },
     }
 }
 
 #[derive(Default)]
 pub struct TraceeBuilder<'dwarf> {
     program: Option<String>,
+    attach_pid: Option<Pid>,
     symbols: Option<Dwarf<borrow::Cow<'dwarf, [u8]>>>,
+    elf_symbols: Option<Vec<ElfSymbol>>,
+    unwind_info: Option<UnwindInfo<'dwarf>>,
     position_ind_p: bool,
 }
 
@@ -263,6 +2768,14 @@ impl<'dwarf> TraceeBuilder<'dwarf> {
         self
     }
 
+    /// Attaches to a running process rather than starting the program,
+    /// which is then only read for its symbols
+    pub fn attach(mut self, pid: Pid) -> Self {
+        self.attach_pid = Some(pid);
+        self
+    }
+
+    #[allow(clippy::wrong_self_convention)]
     pub fn is_position_independent(mut self, pred: bool) -> Self {
         self.position_ind_p = pred;
         self
@@ -273,15 +2786,76 @@ impl<'dwarf> TraceeBuilder<'dwarf> {
         self
     }
 
+    pub fn elf_symbols(mut self, file_buf: &[u8]) -> Self {
+        self.elf_symbols = load_elf_symbols(file_buf).ok();
+        self
+    }
+
+    pub fn unwind_info(mut self, file_buf: &'dwarf [u8]) -> Self {
+        self.unwind_info = load_unwind_info(file_buf).ok();
+        self
+    }
+
     pub fn build(self) -> TraceeDbg<'dwarf> {
         TraceeDbg {
             program: self.program,
+            attach_pid: self.attach_pid,
             breakpoints: RefCell::new(HashMap::default()),
-            symbols: match self.symbols {
-                Some(sym) => Some(RefCell::new(sym)),
-                None => None,
-            },
+            brkpt_groups: RefCell::new(Vec::new()),
+            symbols: self.symbols.map(RefCell::new),
+            elf_symbols: self.elf_symbols,
+            unwind_info: self.unwind_info,
             position_ind_p: self.position_ind_p,
+            inline_skip: Cell::new(0),
+            pending_steps: Cell::new((0, false)),
+            step_over: RefCell::new(None),
+            held_stops: RefCell::new(VecDeque::new()),
+            snapshots: RefCell::new(HashMap::new()),
+            signal_policies: RefCell::new(HashMap::new()),
+            threads: RefCell::new(Vec::new()),
+            current_thread: Cell::new(Pid::from_raw(0)),
+            non_stop: Cell::new(false),
+            follow_fork_child: Cell::new(false),
+            detach_on_fork: Cell::new(true),
+            vfork_traps: RefCell::new(Vec::new()),
+            catchpoints: RefCell::new(Vec::new()),
+            trace_syscalls: Cell::new(false),
+            tracee_pgrp: Cell::new(None),
+        }
+    }
+}
+
+/// Finds a running process by name as `pgrep -x` would, by its command name
+/// or the file name of its executable, the former being cut to 15 characters
+pub fn find_pid_by_name(name: &str) -> Result<Pid, &'static str> {
+    let own_pid = Pid::this();
+
+    let pids: Vec<Pid> = fs::read_dir("/proc")
+        .map_err(|_| "Failed to list running processes!")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .map(Pid::from_raw)
+        .filter(|&pid| pid != own_pid)
+        .filter(|pid| {
+            let comm = fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default();
+            let exe = fs::read_link(format!("/proc/{}/exe", pid)).ok();
+
+            comm.trim_end() == name
+                || exe
+                    .as_deref()
+                    .and_then(|exe| exe.file_name())
+                    .is_some_and(|file| file == name)
+        })
+        .collect();
+
+    match pids.as_slice() {
+        [] => Err("No running process by that name!"),
+        [pid] => Ok(*pid),
+        _ => {
+            println!("Processes named {:?}:", name);
+            for pid in &pids {
+                println!("  {}", pid);
+            }
+            Err("More than one process by that name, please pick one with -p PID!")
         }
     }
 }
@@ -305,14 +2879,14 @@ pub fn run_get_pid_dialogue() -> Pid {
             .map(Pid::from_raw)
             .map_err(|_| "Please supply an integer for PID!")
             .and_then(|pid| {
-                if let Ok(_) = kill(pid, None) {
+                if kill(pid, None).is_ok() {
                     Ok(pid)
                 } else {
                     Err("Process does not exist!")
                 }
             });
 
-        if let Ok(_) = pid {
+        if pid.is_ok() {
             break;
         }
 
@@ -321,3 +2895,191 @@ pub fn run_get_pid_dialogue() -> Pid {
 
     pid.unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_string_literal_unescapes() {
+        assert_eq!(
+            parse_string_literal("plain text"),
+            Ok(b"plain text".to_vec())
+        );
+        assert_eq!(
+            parse_string_literal(r#"a\nb\tc\rd\0e\\f\"g\'h"#),
+            Ok(b"a\nb\tc\rd\0e\\f\"g'h".to_vec())
+        );
+        assert_eq!(
+            parse_string_literal(r"\x41\x7a\xFF"),
+            Ok(vec![0x41, 0x7a, 0xff])
+        );
+        assert_eq!(parse_string_literal("é"), Ok("é".as_bytes().to_vec()));
+        assert_eq!(parse_string_literal(""), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn parse_string_literal_takes_two_hex_digits() {
+        assert_eq!(parse_string_literal(r"\x414"), Ok(b"A4".to_vec()));
+        assert_eq!(parse_string_literal(r"\x4"), Ok(vec![0x04]));
+    }
+
+    #[test]
+    fn parse_string_literal_rejects_bad_escapes() {
+        for string in [r"\q", r"trailing\", r"\x", r"\xzz", r"\x-1"] {
+            assert!(parse_string_literal(string).is_err(), "{}", string);
+        }
+    }
+
+    #[test]
+    fn encode_typed_value_sizes_integers() {
+        assert_eq!(encode_typed_value("char", "65"), Ok(vec![0x41]));
+        assert_eq!(encode_typed_value("short", "0x1234"), Ok(vec![0x34, 0x12]));
+        assert_eq!(encode_typed_value("int", "-1"), Ok(vec![0xff; 4]));
+        assert_eq!(
+            encode_typed_value("uint64_t", "0x0102030405060708"),
+            Ok(vec![8, 7, 6, 5, 4, 3, 2, 1])
+        );
+        assert_eq!(
+            encode_typed_value("char *", "0x10"),
+            Ok(vec![0x10, 0, 0, 0, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn encode_typed_value_encodes_floats() {
+        assert_eq!(
+            encode_typed_value("float", "1.5"),
+            Ok(1.5f32.to_le_bytes().to_vec())
+        );
+        assert_eq!(
+            encode_typed_value("f64", "-0.25"),
+            Ok((-0.25f64).to_le_bytes().to_vec())
+        );
+        assert_eq!(
+            encode_typed_value("double", "inf"),
+            Ok(f64::INFINITY.to_le_bytes().to_vec())
+        );
+        assert!(encode_typed_value("float", "one").is_err());
+    }
+
+    #[test]
+    fn encode_typed_value_takes_char_literals() {
+        assert_eq!(encode_typed_value("char", "'A'"), Ok(vec![b'A']));
+        assert_eq!(encode_typed_value("u8", r"'\n'"), Ok(vec![b'\n']));
+        assert_eq!(encode_typed_value("char", r"'\x00'"), Ok(vec![0]));
+        assert!(encode_typed_value("char", "'AB'").is_err());
+        assert!(encode_typed_value("char", "''").is_err());
+
+        // Only char-sized stores take them
+        assert!(encode_typed_value("int", "'A'").is_err());
+    }
+
+    #[test]
+    fn encode_typed_value_rejects_unknown_types() {
+        assert!(encode_typed_value("quad", "1").is_err());
+        assert!(encode_typed_value("int", "ten").is_err());
+    }
+
+    #[test]
+    fn parse_search_pattern_encodes_strings() {
+        assert_eq!(parse_search_pattern(r#""abc""#), Ok(b"abc".to_vec()));
+        assert_eq!(parse_search_pattern(r#""a b\n""#), Ok(b"a b\n".to_vec()));
+        assert_eq!(parse_search_pattern(r#"u"hi""#), Ok(vec![b'h', 0, b'i', 0]));
+        assert_eq!(parse_search_pattern(r#"u"é""#), Ok(vec![0xe9, 0]));
+        assert!(parse_search_pattern(r#""unterminated"#).is_err());
+        assert!(parse_search_pattern("unquoted").is_err());
+    }
+
+    #[test]
+    fn parse_search_pattern_encodes_bytes_and_integers() {
+        assert_eq!(
+            parse_search_pattern("/x 41 0x42 ff"),
+            Ok(vec![0x41, 0x42, 0xff])
+        );
+        assert_eq!(parse_search_pattern("/b 0x7f"), Ok(vec![0x7f]));
+        assert_eq!(parse_search_pattern("/h 0x1234"), Ok(vec![0x34, 0x12]));
+        assert_eq!(parse_search_pattern("/w -1"), Ok(vec![0xff; 4]));
+        assert_eq!(
+            parse_search_pattern("/g 1"),
+            Ok(vec![1, 0, 0, 0, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn parse_search_pattern_rejects_bad_patterns() {
+        for pattern in ["/x 4g", "/x 100", "/q 1", "/w", "/w ten"] {
+            assert!(parse_search_pattern(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn parse_mem_range_parses_address_ranges() {
+        assert!(matches!(
+            parse_mem_range("1000,2000"),
+            Ok(MemRange::Addrs(0x1000, 0x2000))
+        ));
+        assert!(matches!(
+            parse_mem_range("0x1000, 0x2000"),
+            Ok(MemRange::Addrs(0x1000, 0x2000))
+        ));
+        assert!(matches!(
+            parse_mem_range("1000,+0x10"),
+            Ok(MemRange::Addrs(0x1000, 0x1010))
+        ));
+        assert!(matches!(
+            parse_mem_range("1000,+16"),
+            Ok(MemRange::Addrs(0x1000, 0x1010))
+        ));
+    }
+
+    #[test]
+    fn parse_mem_range_takes_mapping_names() {
+        assert!(matches!(
+            parse_mem_range("[heap]"),
+            Ok(MemRange::Mapping(name)) if name == "[heap]"
+        ));
+        assert!(matches!(
+            parse_mem_range("libc.so.6"),
+            Ok(MemRange::Mapping(name)) if name == "libc.so.6"
+        ));
+    }
+
+    #[test]
+    fn parse_mem_range_rejects_empty_and_backwards_ranges() {
+        for range in [
+            "2000,1000",
+            "1000,1000",
+            "1000,+0",
+            "1000,",
+            ",1000",
+            "zz,1000",
+        ] {
+            assert!(parse_mem_range(range).is_err(), "{}", range);
+        }
+    }
+
+    #[test]
+    fn parse_mem_range_rejects_overflowing_ranges() {
+        assert_eq!(
+            parse_mem_range("7ffffffde000,+-1").unwrap_err(),
+            "Range length cannot be negative!"
+        );
+        assert_eq!(
+            parse_mem_range("555555555155,+0xffffffffffffffff").unwrap_err(),
+            "Range end overflows the address space!"
+        );
+        assert_eq!(
+            parse_mem_range("ffffffffffffffff,+1").unwrap_err(),
+            "Range end overflows the address space!"
+        );
+        assert!(parse_mem_range("1000,+18446744073709551616").is_err());
+    }
+
+    #[test]
+    fn parse_addr_range_matches_disas_forms() {
+        assert_eq!(parse_addr_range("1000", "+8"), Ok((0x1000, 0x1008)));
+        assert_eq!(parse_addr_range("1000", "1008"), Ok((0x1000, 0x1008)));
+        assert!(parse_addr_range("555555555155", "+0xffffffffffffffff").is_err());
+    }
+}
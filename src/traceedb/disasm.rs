@@ -0,0 +1,65 @@
+use iced_x86::{
+    Decoder, DecoderError, DecoderOptions, FlowControl, Formatter, GasFormatter, Instruction,
+};
+
+/// Longest encoding an x86-64 instruction can have
+pub const MAX_INSN_LEN: usize = 15;
+
+/// A decoded instruction, formatted in AT&T syntax to match the .S samples
+#[derive(Debug)]
+pub struct DisasmInsn {
+    pub addr: u64,
+    pub len: usize,
+    pub text: String,
+    pub branch_target: Option<u64>,
+    pub is_call: bool,
+}
+
+impl DisasmInsn {
+    pub fn next_addr(&self) -> u64 {
+        self.addr + self.len as u64
+    }
+}
+
+/// Decodes up to `max_insns` instructions from code that was read starting
+/// at `addr`. Decoding stops early rather than return an instruction that
+/// runs off the end of the buffer.
+pub fn disassemble(code: &[u8], addr: u64, max_insns: usize) -> Vec<DisasmInsn> {
+    let mut decoder = Decoder::with_ip(64, code, addr, DecoderOptions::NONE);
+    let mut formatter = GasFormatter::new();
+    let mut insn = Instruction::default();
+    let mut insns = Vec::new();
+
+    while decoder.can_decode() && insns.len() < max_insns {
+        decoder.decode_out(&mut insn);
+
+        if decoder.last_error() == DecoderError::NoMoreBytes {
+            break;
+        }
+
+        let mut text = String::new();
+        if insn.is_invalid() {
+            text.push_str("(bad)");
+        } else {
+            formatter.format(&insn, &mut text);
+        }
+
+        let is_branch = matches!(
+            insn.flow_control(),
+            FlowControl::Call | FlowControl::UnconditionalBranch | FlowControl::ConditionalBranch
+        );
+
+        insns.push(DisasmInsn {
+            addr: insn.ip(),
+            len: insn.len(),
+            text,
+            branch_target: Some(insn.near_branch_target()).filter(|addr| is_branch && *addr != 0),
+            is_call: matches!(
+                insn.flow_control(),
+                FlowControl::Call | FlowControl::IndirectCall
+            ),
+        });
+    }
+
+    insns
+}
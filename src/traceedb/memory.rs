@@ -0,0 +1,90 @@
+use nix::{
+    sys::uio::{process_vm_readv, process_vm_writev, RemoteIoVec},
+    unistd::Pid,
+};
+use procmaps::Mappings;
+
+use std::fs::{File, OpenOptions};
+use std::io::{IoSlice, IoSliceMut};
+use std::os::unix::fs::FileExt;
+
+/// Copies of tracee memory as (start address, bytes) pairs
+pub type MemRegions = Vec<(u64, Vec<u8>)>;
+
+/// Most read in one go, as the buffer is allocated up front
+pub const MAX_READ_LEN: usize = 1 << 30;
+
+/// Reads longer than this are checked against the mappings before a buffer
+/// is allocated, so an unmapped range fails rather than exhausting memory
+const CHECKED_READ_LEN: usize = 1 << 16;
+
+/// Reads a range of tracee memory in a single syscall. process_vm_readv
+/// honours page protections, so ranges it can't fully read are retried
+/// through /proc/pid/mem, which the tracer may read regardless.
+pub fn read_bytes(pid: Pid, addr: u64, len: usize) -> Result<Vec<u8>, &'static str> {
+    if len > MAX_READ_LEN {
+        return Err("Too much memory to read at once!");
+    }
+
+    let end = addr
+        .checked_add(len as u64)
+        .ok_or("Cannot access tracee memory at that address!")?;
+
+    if len > CHECKED_READ_LEN && !is_mapped(pid, addr, end) {
+        return Err("Cannot access tracee memory at that address!");
+    }
+
+    let mut bytes = vec![0; len];
+    let remote = RemoteIoVec {
+        base: addr as usize,
+        len,
+    };
+
+    if process_vm_readv(pid, &mut [IoSliceMut::new(&mut bytes)], &[remote]) == Ok(len) {
+        return Ok(bytes);
+    }
+
+    File::open(format!("/proc/{}/mem", pid))
+        .map_err(|_| "Failed to open tracee memory!")?
+        .read_exact_at(&mut bytes, addr)
+        .map_err(|_| "Cannot access tracee memory at that address!")?;
+
+    Ok(bytes)
+}
+
+/// Whether mappings cover the whole of a range, with no gaps
+fn is_mapped(pid: Pid, start: u64, end: u64) -> bool {
+    let Ok(mappings) = Mappings::from_pid(pid.into()) else {
+        return false;
+    };
+
+    let mut covered = start;
+    for map in mappings.iter() {
+        if (map.base as u64) <= covered && covered < map.ceiling as u64 {
+            covered = map.ceiling as u64;
+        }
+    }
+
+    covered >= end
+}
+
+/// Writes a range of tracee memory, going through /proc/pid/mem for pages
+/// that aren't writable (.text, mostly) as the kernel lets a tracer poke
+/// those the same way PTRACE_POKEDATA would.
+pub fn write_bytes(pid: Pid, addr: u64, bytes: &[u8]) -> Result<(), &'static str> {
+    let remote = RemoteIoVec {
+        base: addr as usize,
+        len: bytes.len(),
+    };
+
+    if process_vm_writev(pid, &[IoSlice::new(bytes)], &[remote]) == Ok(bytes.len()) {
+        return Ok(());
+    }
+
+    OpenOptions::new()
+        .write(true)
+        .open(format!("/proc/{}/mem", pid))
+        .map_err(|_| "Failed to open tracee memory!")?
+        .write_all_at(bytes, addr)
+        .map_err(|_| "Cannot write tracee memory at that address!")
+}
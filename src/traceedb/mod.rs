@@ -1,4 +1,12 @@
 pub mod breakpoint;
+pub mod catchpoint;
 pub mod command;
 pub mod dbg;
+pub mod disasm;
+pub mod memory;
+pub mod register;
+pub mod signal;
 pub mod symbol;
+pub mod syscall;
+pub mod thread;
+pub mod unwind;
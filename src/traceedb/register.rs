@@ -0,0 +1,619 @@
+use nix::{errno::Errno, libc, libc::user_regs_struct, unistd::Pid};
+
+use std::ffi::c_void;
+use std::ptr;
+
+/// Every register in user_regs_struct, in the order they are displayed
+pub const GP_REGISTERS: [&str; 27] = [
+    "rip", "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12",
+    "r13", "r14", "r15", "eflags", "cs", "ss", "ds", "es", "fs", "gs", "fs_base", "gs_base",
+    "orig_rax",
+];
+
+const EFLAGS_BITS: [(u64, &str); 14] = [
+    (0, "CF"),
+    (2, "PF"),
+    (4, "AF"),
+    (6, "ZF"),
+    (7, "SF"),
+    (8, "TF"),
+    (9, "IF"),
+    (10, "DF"),
+    (11, "OF"),
+    (14, "NT"),
+    (16, "RF"),
+    (17, "VM"),
+    (18, "AC"),
+    (21, "ID"),
+];
+
+/// Accepts "rax", "RAX", "%rax" and "$rax" alike
+pub fn normalize_reg_name(name: &str) -> Option<String> {
+    let name = name.trim_start_matches(['$', '%']).to_ascii_lowercase();
+
+    GP_REGISTERS.contains(&name.as_str()).then_some(name)
+}
+
+pub fn reg_mut<'a>(regs: &'a mut user_regs_struct, name: &str) -> Option<&'a mut u64> {
+    let reg = match name {
+        "rip" => &mut regs.rip,
+        "rax" => &mut regs.rax,
+        "rbx" => &mut regs.rbx,
+        "rcx" => &mut regs.rcx,
+        "rdx" => &mut regs.rdx,
+        "rsi" => &mut regs.rsi,
+        "rdi" => &mut regs.rdi,
+        "rbp" => &mut regs.rbp,
+        "rsp" => &mut regs.rsp,
+        "r8" => &mut regs.r8,
+        "r9" => &mut regs.r9,
+        "r10" => &mut regs.r10,
+        "r11" => &mut regs.r11,
+        "r12" => &mut regs.r12,
+        "r13" => &mut regs.r13,
+        "r14" => &mut regs.r14,
+        "r15" => &mut regs.r15,
+        "eflags" => &mut regs.eflags,
+        "cs" => &mut regs.cs,
+        "ss" => &mut regs.ss,
+        "ds" => &mut regs.ds,
+        "es" => &mut regs.es,
+        "fs" => &mut regs.fs,
+        "gs" => &mut regs.gs,
+        "fs_base" => &mut regs.fs_base,
+        "gs_base" => &mut regs.gs_base,
+        "orig_rax" => &mut regs.orig_rax,
+        _ => return None,
+    };
+
+    Some(reg)
+}
+
+pub fn reg_value(regs: &user_regs_struct, name: &str) -> Option<u64> {
+    let mut regs = *regs;
+    reg_mut(&mut regs, name).map(|reg| *reg)
+}
+
+/// Names the flags set in %eflags, e.g. "[ PF ZF IF ]"
+pub fn decode_eflags(eflags: u64) -> String {
+    let flags: Vec<&str> = EFLAGS_BITS
+        .iter()
+        .filter(|(bit, _)| eflags & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+
+    format!("[ {} ]", flags.join(" "))
+}
+
+const NT_X86_XSTATE: usize = 0x202;
+
+// Standard (non-compacted) XSAVE layout, as handed out by PTRACE_GETREGSET
+const FXSAVE_SIZE: usize = 512;
+const ST_OFFSET: usize = 32;
+const XMM_OFFSET: usize = 160;
+const MXCSR_OFFSET: usize = 24;
+// The kernel stashes XCR0 in the software-reserved bytes for ptrace
+const XCR0_OFFSET: usize = 464;
+const XSTATE_BV_OFFSET: usize = 512;
+const YMM_HI128_OFFSET: usize = 576;
+const ZMM_HI256_OFFSET: usize = 1152;
+const HI16_ZMM_OFFSET: usize = 1664;
+// Big enough for AMX tile data; the kernel tells us how much it filled in,
+// and expects exactly that much back
+const XSAVE_MAX_SIZE: usize = 16384;
+
+const XFEATURE_SSE: u64 = 1 << 1;
+const XFEATURE_AVX: u64 = 1 << 2;
+const XFEATURE_AVX512: u64 = 0b111 << 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorKind {
+    Xmm,
+    Ymm,
+    Zmm,
+}
+
+/// Ways of slicing up a vector register's bytes into lanes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaneView {
+    F32,
+    F64,
+    I8,
+    I16,
+    I32,
+    I64,
+}
+
+impl LaneView {
+    pub fn width(&self) -> usize {
+        match self {
+            LaneView::I8 => 1,
+            LaneView::I16 => 2,
+            LaneView::F32 | LaneView::I32 => 4,
+            LaneView::F64 | LaneView::I64 => 8,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "f32" | "v4_float" | "v8_float" | "v16_float" => Some(LaneView::F32),
+            "f64" | "v2_double" | "v4_double" | "v8_double" => Some(LaneView::F64),
+            "i8" | "u8" => Some(LaneView::I8),
+            "i16" | "u16" => Some(LaneView::I16),
+            "i32" | "u32" => Some(LaneView::I32),
+            "i64" | "u64" => Some(LaneView::I64),
+            _ => None,
+        }
+    }
+}
+
+/// What a "set $..." aimed at the FPU/vector state writes to: a whole x87
+/// or vector register, or a single lane of a vector register
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FpTarget {
+    St(usize),
+    Vector(VectorKind, usize),
+    Lane(VectorKind, usize, LaneView, usize),
+}
+
+/// The x87/SSE/AVX register file of a stopped tracee, read as one XSAVE
+/// area (or just the legacy FXSAVE region on kernels without XSTATE).
+pub struct FpState {
+    area: Vec<u8>,
+    has_xstate: bool,
+}
+
+impl FpState {
+    pub fn read(pid: Pid) -> Result<Self, &'static str> {
+        let mut area = vec![0u8; XSAVE_MAX_SIZE];
+        let mut iov = libc::iovec {
+            iov_base: area.as_mut_ptr() as *mut c_void,
+            iov_len: area.len(),
+        };
+
+        let res = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETREGSET,
+                pid.as_raw(),
+                NT_X86_XSTATE as *mut c_void,
+                &mut iov as *mut libc::iovec,
+            )
+        };
+
+        if Errno::result(res).is_ok() {
+            area.truncate(iov.iov_len);
+            return Ok(FpState {
+                area,
+                has_xstate: true,
+            });
+        }
+
+        area.truncate(FXSAVE_SIZE);
+        let res = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GETFPREGS,
+                pid.as_raw(),
+                ptr::null_mut::<c_void>(),
+                area.as_mut_ptr() as *mut c_void,
+            )
+        };
+
+        Errno::result(res)
+            .map(|_| FpState {
+                area,
+                has_xstate: false,
+            })
+            .map_err(|err_no| {
+                eprintln!("ERRNO {}", err_no);
+                "failed to PTRACE_GETFPREGS"
+            })
+    }
+
+    pub fn write(&self, pid: Pid) -> Result<(), &'static str> {
+        let mut area = self.area.clone();
+
+        let res = if self.has_xstate {
+            let mut iov = libc::iovec {
+                iov_base: area.as_mut_ptr() as *mut c_void,
+                iov_len: area.len(),
+            };
+
+            unsafe {
+                libc::ptrace(
+                    libc::PTRACE_SETREGSET,
+                    pid.as_raw(),
+                    NT_X86_XSTATE as *mut c_void,
+                    &mut iov as *mut libc::iovec,
+                )
+            }
+        } else {
+            unsafe {
+                libc::ptrace(
+                    libc::PTRACE_SETFPREGS,
+                    pid.as_raw(),
+                    ptr::null_mut::<c_void>(),
+                    area.as_mut_ptr() as *mut c_void,
+                )
+            }
+        };
+
+        Errno::result(res).map(|_| ()).map_err(|err_no| {
+            eprintln!("ERRNO {}", err_no);
+            "failed to write back floating-point registers"
+        })
+    }
+
+    /// The state components this kernel/CPU save for us (XCR0)
+    pub fn xfeatures(&self) -> u64 {
+        if self.has_xstate {
+            self.read_u64(XCR0_OFFSET)
+        } else {
+            0b11
+        }
+    }
+
+    pub fn has_avx(&self) -> bool {
+        self.xfeatures() & XFEATURE_AVX != 0
+    }
+
+    pub fn has_avx512(&self) -> bool {
+        self.xfeatures() & XFEATURE_AVX512 == XFEATURE_AVX512
+    }
+
+    pub fn fcw(&self) -> u16 {
+        u16::from_le_bytes([self.area[0], self.area[1]])
+    }
+
+    pub fn fsw(&self) -> u16 {
+        u16::from_le_bytes([self.area[2], self.area[3]])
+    }
+
+    pub fn ftw(&self) -> u8 {
+        self.area[4]
+    }
+
+    pub fn fop(&self) -> u16 {
+        u16::from_le_bytes([self.area[6], self.area[7]])
+    }
+
+    pub fn mxcsr(&self) -> u32 {
+        let bytes = &self.area[MXCSR_OFFSET..MXCSR_OFFSET + 4];
+        u32::from_le_bytes(bytes.try_into().unwrap())
+    }
+
+    pub fn st(&self, idx: usize) -> [u8; 10] {
+        let offset = ST_OFFSET + 16 * idx;
+        self.area[offset..offset + 10].try_into().unwrap()
+    }
+
+    pub fn set_st(&mut self, idx: usize, bytes: [u8; 10]) {
+        let offset = ST_OFFSET + 16 * idx;
+        self.area[offset..offset + 10].copy_from_slice(&bytes);
+    }
+
+    /// Pieces a vector register together from wherever XSAVE keeps its parts
+    pub fn vector(&self, kind: VectorKind, idx: usize) -> Option<Vec<u8>> {
+        self.vector_parts(kind, idx).map(|parts| {
+            parts
+                .iter()
+                .flat_map(|(offset, len)| self.area[*offset..*offset + *len].to_vec())
+                .collect()
+        })
+    }
+
+    pub fn set_vector(&mut self, kind: VectorKind, idx: usize, bytes: &[u8]) -> Option<()> {
+        let parts = self.vector_parts(kind, idx)?;
+
+        let mut consumed = 0;
+        for (offset, len) in parts {
+            self.area[offset..offset + len].copy_from_slice(&bytes[consumed..consumed + len]);
+            consumed += len;
+        }
+
+        // Components left in their init state get ignored on restore unless flagged
+        if self.has_xstate {
+            let mut xstate_bv = self.read_u64(XSTATE_BV_OFFSET) | XFEATURE_SSE;
+            match kind {
+                VectorKind::Xmm => {}
+                VectorKind::Ymm => xstate_bv |= XFEATURE_AVX,
+                VectorKind::Zmm => xstate_bv |= XFEATURE_AVX | XFEATURE_AVX512,
+            }
+            self.area[XSTATE_BV_OFFSET..XSTATE_BV_OFFSET + 8]
+                .copy_from_slice(&xstate_bv.to_le_bytes());
+        }
+
+        Some(())
+    }
+
+    fn vector_parts(&self, kind: VectorKind, idx: usize) -> Option<Vec<(usize, usize)>> {
+        let parts = match kind {
+            VectorKind::Xmm if idx < 16 => vec![(XMM_OFFSET + 16 * idx, 16)],
+
+            VectorKind::Ymm if idx < 16 && self.has_avx() => vec![
+                (XMM_OFFSET + 16 * idx, 16),
+                (YMM_HI128_OFFSET + 16 * idx, 16),
+            ],
+
+            VectorKind::Zmm if idx < 16 && self.has_avx512() => vec![
+                (XMM_OFFSET + 16 * idx, 16),
+                (YMM_HI128_OFFSET + 16 * idx, 16),
+                (ZMM_HI256_OFFSET + 32 * idx, 32),
+            ],
+
+            VectorKind::Zmm if idx < 32 && self.has_avx512() => {
+                vec![(HI16_ZMM_OFFSET + 64 * (idx - 16), 64)]
+            }
+
+            _ => return None,
+        };
+
+        let end = parts.iter().map(|(offset, len)| offset + len).max()?;
+        (end <= self.area.len()).then_some(parts)
+    }
+
+    fn read_u64(&self, offset: usize) -> u64 {
+        self.area
+            .get(offset..offset + 8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .unwrap_or(0)
+    }
+}
+
+/// Parses "st0", "xmm3", "ymm15", "zmm20" and lanes like "xmm0.f32[2]"
+pub fn parse_fp_target(name: &str) -> Option<FpTarget> {
+    let name = name.trim_start_matches(['$', '%']).to_ascii_lowercase();
+
+    let (reg, lane) = match name.split_once('.') {
+        Some((reg, lane)) => (reg.to_string(), Some(lane.to_string())),
+        None => (name, None),
+    };
+
+    if let Some(idx) = reg
+        .strip_prefix("st")
+        .and_then(|idx| idx.parse::<usize>().ok())
+    {
+        return (idx < 8 && lane.is_none()).then_some(FpTarget::St(idx));
+    }
+
+    let (kind, idx) = [
+        ("xmm", VectorKind::Xmm),
+        ("ymm", VectorKind::Ymm),
+        ("zmm", VectorKind::Zmm),
+    ]
+    .iter()
+    .find_map(|(prefix, kind)| {
+        let idx = reg.strip_prefix(prefix)?.parse::<usize>().ok()?;
+        Some((*kind, idx))
+    })?;
+
+    match lane {
+        None => Some(FpTarget::Vector(kind, idx)),
+        Some(lane) => {
+            let (view, lane_idx) = lane.strip_suffix(']')?.split_once('[')?;
+            let view = LaneView::from_name(view)?;
+            let lane_idx = lane_idx.parse::<usize>().ok()?;
+
+            (lane_idx < vector_width(kind) / view.width())
+                .then_some(FpTarget::Lane(kind, idx, view, lane_idx))
+        }
+    }
+}
+
+pub fn vector_width(kind: VectorKind) -> usize {
+    match kind {
+        VectorKind::Xmm => 16,
+        VectorKind::Ymm => 32,
+        VectorKind::Zmm => 64,
+    }
+}
+
+/// Splits a register's bytes into lanes and renders them, e.g. "{1, 2.5}"
+pub fn format_lanes(bytes: &[u8], view: LaneView) -> String {
+    let lanes: Vec<String> = bytes
+        .chunks_exact(view.width())
+        .map(|lane| match view {
+            LaneView::F32 => format_float(f32::from_le_bytes(lane.try_into().unwrap()) as f64),
+            LaneView::F64 => format_float(f64::from_le_bytes(lane.try_into().unwrap())),
+            LaneView::I8 => (lane[0] as i8).to_string(),
+            LaneView::I16 => i16::from_le_bytes(lane.try_into().unwrap()).to_string(),
+            LaneView::I32 => i32::from_le_bytes(lane.try_into().unwrap()).to_string(),
+            LaneView::I64 => i64::from_le_bytes(lane.try_into().unwrap()).to_string(),
+        })
+        .collect();
+
+    format!("{{{}}}", lanes.join(", "))
+}
+
+/// Prints floats the way a human wants to read them, switching to
+/// scientific notation for very large and very small magnitudes
+pub fn format_float(val: f64) -> String {
+    let magnitude = val.abs();
+
+    if magnitude != 0.0 && magnitude.is_finite() && !(1e-6..1e16).contains(&magnitude) {
+        format!("{:e}", val)
+    } else {
+        val.to_string()
+    }
+}
+
+/// Renders little-endian register bytes as one big hex number
+pub fn format_raw(bytes: &[u8]) -> String {
+    let digits: String = bytes
+        .iter()
+        .rev()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("0x{}", digits)
+}
+
+/// Converts an x87 80-bit extended precision value for display
+pub fn f80_to_f64(bytes: [u8; 10]) -> f64 {
+    let mantissa = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let sign_exp = u16::from_le_bytes([bytes[8], bytes[9]]);
+    let sign = if sign_exp & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exp = (sign_exp & 0x7FFF) as i32;
+
+    match exp {
+        0 if mantissa == 0 => sign * 0.0,
+        0x7FFF if mantissa << 1 == 0 => sign * f64::INFINITY,
+        0x7FFF => f64::NAN,
+        // Denormals have an exponent of 1 - bias, not 0 - bias
+        _ => {
+            let exp = if exp == 0 { 1 } else { exp } - 16383;
+            let fraction = mantissa as f64 * 2f64.powi(-63);
+
+            // Scaled in halves, as powi goes through 2^-exp and 2^1074
+            // already overflows
+            sign * fraction * 2f64.powi(exp / 2) * 2f64.powi(exp - exp / 2)
+        }
+    }
+}
+
+/// Converts a double into the x87 80-bit extended precision format
+pub fn f64_to_f80(val: f64) -> [u8; 10] {
+    let bits = val.to_bits();
+    let sign = ((bits >> 63) as u16) << 15;
+    let exp = ((bits >> 52) & 0x7FF) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+
+    let (exp, mantissa) = match exp {
+        0 if fraction == 0 => (0, 0),
+        0x7FF => (0x7FFF, (1 << 63) | (fraction << 11)),
+        // Double denormals are normal numbers in the wider format
+        0 => {
+            let shift = fraction.leading_zeros() - 11;
+            (1 - 1023 + 16383 - shift as i32, fraction << (11 + shift))
+        }
+        _ => (exp - 1023 + 16383, (1 << 63) | (fraction << 11)),
+    };
+
+    let mut bytes = [0u8; 10];
+    bytes[..8].copy_from_slice(&mantissa.to_le_bytes());
+    bytes[8..].copy_from_slice(&(sign | exp as u16).to_le_bytes());
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f80(mantissa: u64, sign_exp: u16) -> [u8; 10] {
+        let mut bytes = [0u8; 10];
+        bytes[..8].copy_from_slice(&mantissa.to_le_bytes());
+        bytes[8..].copy_from_slice(&sign_exp.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn f64_to_f80_encodes_normals() {
+        assert_eq!(f64_to_f80(1.0), f80(1 << 63, 0x3FFF));
+        assert_eq!(f64_to_f80(-2.0), f80(1 << 63, 0xC000));
+        assert_eq!(f64_to_f80(1.5), f80(0xC000_0000_0000_0000, 0x3FFF));
+    }
+
+    #[test]
+    fn f64_to_f80_encodes_zeros_and_infinities() {
+        assert_eq!(f64_to_f80(0.0), f80(0, 0));
+        assert_eq!(f64_to_f80(-0.0), f80(0, 0x8000));
+        assert_eq!(f64_to_f80(f64::INFINITY), f80(1 << 63, 0x7FFF));
+        assert_eq!(f64_to_f80(f64::NEG_INFINITY), f80(1 << 63, 0xFFFF));
+    }
+
+    #[test]
+    fn f64_to_f80_normalizes_double_denormals() {
+        // The smallest double denormal is 2^-1074
+        assert_eq!(f64_to_f80(5e-324), f80(1 << 63, (16383 - 1074) as u16));
+    }
+
+    #[test]
+    fn f80_round_trips_doubles() {
+        for val in [
+            1.0,
+            -1.0,
+            0.1,
+            -123456.789,
+            f64::MAX,
+            f64::MIN,
+            f64::MIN_POSITIVE,
+            5e-324,
+            -1e-310,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ] {
+            assert_eq!(f80_to_f64(f64_to_f80(val)), val, "{}", val);
+        }
+
+        let zero = f80_to_f64(f64_to_f80(-0.0));
+        assert!(zero == 0.0 && zero.is_sign_negative());
+        assert!(f80_to_f64(f64_to_f80(f64::NAN)).is_nan());
+    }
+
+    #[test]
+    fn f80_to_f64_handles_values_doubles_cannot_hold() {
+        // Beyond a double's range either way
+        assert_eq!(f80_to_f64(f80(1 << 63, 0x7FFE)), f64::INFINITY);
+        assert_eq!(f80_to_f64(f80(1 << 63, 0xFFFE)), f64::NEG_INFINITY);
+        assert_eq!(f80_to_f64(f80(1 << 63, 0x0001)), 0.0);
+
+        // An x87 denormal, exponent field 0 but a non-zero mantissa
+        assert_eq!(f80_to_f64(f80(1, 0)), 0.0);
+
+        // NaNs, quiet or signalling
+        assert!(f80_to_f64(f80(0xC000_0000_0000_0000, 0x7FFF)).is_nan());
+        assert!(f80_to_f64(f80(0x8000_0000_0000_0001, 0xFFFF)).is_nan());
+    }
+
+    #[test]
+    fn parse_fp_target_parses_registers() {
+        assert_eq!(parse_fp_target("st0"), Some(FpTarget::St(0)));
+        assert_eq!(parse_fp_target("$st7"), Some(FpTarget::St(7)));
+        assert_eq!(
+            parse_fp_target("xmm3"),
+            Some(FpTarget::Vector(VectorKind::Xmm, 3))
+        );
+        assert_eq!(
+            parse_fp_target("%YMM15"),
+            Some(FpTarget::Vector(VectorKind::Ymm, 15))
+        );
+        assert_eq!(
+            parse_fp_target("zmm20"),
+            Some(FpTarget::Vector(VectorKind::Zmm, 20))
+        );
+    }
+
+    #[test]
+    fn parse_fp_target_parses_lanes() {
+        assert_eq!(
+            parse_fp_target("xmm0.f32[3]"),
+            Some(FpTarget::Lane(VectorKind::Xmm, 0, LaneView::F32, 3))
+        );
+        assert_eq!(
+            parse_fp_target("ymm1.u8[31]"),
+            Some(FpTarget::Lane(VectorKind::Ymm, 1, LaneView::I8, 31))
+        );
+        assert_eq!(
+            parse_fp_target("zmm2.v8_double[7]"),
+            Some(FpTarget::Lane(VectorKind::Zmm, 2, LaneView::F64, 7))
+        );
+    }
+
+    #[test]
+    fn parse_fp_target_rejects_bad_names() {
+        for name in [
+            "st8",
+            "st0.f32[0]",
+            "st",
+            "xmm",
+            "mm0",
+            "xmm0.f32[4]",
+            "ymm0.f64[4]",
+            "zmm0.i64[8]",
+            "xmm0.f32",
+            "xmm0.f32[]",
+            "xmm0.f32[-1]",
+            "xmm0.q32[0]",
+        ] {
+            assert_eq!(parse_fp_target(name), None, "{}", name);
+        }
+    }
+}
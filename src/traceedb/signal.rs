@@ -0,0 +1,138 @@
+use nix::{libc, sys::signal::Signal};
+
+use std::str::FromStr;
+
+/// What to do when the tracee receives a signal: whether to return to the
+/// prompt, whether to say so, and whether the signal is handed on to the
+/// tracee when it resumes rather than being swallowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalPolicy {
+    pub stop: bool,
+    pub print: bool,
+    pub pass: bool,
+}
+
+impl SignalPolicy {
+    /// Follows gdb: signals are stopped on and passed along, except for the
+    /// ones programs routinely get in normal operation, and SIGTRAP/SIGINT
+    /// which are the debugger's own.
+    pub fn default_for(signal: Signal) -> Self {
+        match signal {
+            Signal::SIGTRAP | Signal::SIGINT => SignalPolicy {
+                stop: true,
+                print: true,
+                pass: false,
+            },
+
+            Signal::SIGCHLD
+            | Signal::SIGWINCH
+            | Signal::SIGURG
+            | Signal::SIGALRM
+            | Signal::SIGPROF
+            | Signal::SIGVTALRM
+            | Signal::SIGIO => SignalPolicy {
+                stop: false,
+                print: false,
+                pass: true,
+            },
+
+            _ => SignalPolicy {
+                stop: true,
+                print: true,
+                pass: true,
+            },
+        }
+    }
+
+    /// Applies a `handle` keyword. As in gdb, stopping on a signal implies
+    /// printing it and not printing it implies not stopping.
+    pub fn apply(&mut self, action: &str) -> Result<(), &'static str> {
+        match action {
+            "stop" => (self.stop, self.print) = (true, true),
+            "nostop" => self.stop = false,
+            "print" => self.print = true,
+            "noprint" => (self.stop, self.print) = (false, false),
+            "pass" | "noignore" => self.pass = true,
+            "nopass" | "ignore" => self.pass = false,
+            _ => return Err("Unknown action, please use [no]stop, [no]print or [no]pass!"),
+        }
+
+        Ok(())
+    }
+}
+
+/// Accepts "SIGUSR1", "USR1", or the signal's number, in any case
+pub fn parse_signal(name: &str) -> Option<Signal> {
+    if let Ok(signo) = name.parse::<i32>() {
+        return Signal::try_from(signo).ok();
+    }
+
+    let name = name.to_ascii_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{}", name)
+    };
+
+    Signal::from_str(&name).ok()
+}
+
+/// Signals the tracee gets for doing something wrong, rather than being
+/// told to; stopping on one of these warrants a crash report
+pub fn is_crash(signal: Signal) -> bool {
+    matches!(
+        signal,
+        Signal::SIGSEGV | Signal::SIGBUS | Signal::SIGABRT | Signal::SIGFPE | Signal::SIGILL
+    )
+}
+
+/// Whether si_addr is meaningful: the kernel only fills it in for faults,
+/// and SIGSEGV's SI_KERNEL (a general protection fault) leaves it zeroed
+pub fn has_fault_addr(signal: Signal, si_code: i32) -> bool {
+    matches!(
+        signal,
+        Signal::SIGSEGV | Signal::SIGBUS | Signal::SIGFPE | Signal::SIGILL
+    ) && si_code > 0
+        && si_code != libc::SI_KERNEL
+}
+
+/// What a siginfo's si_code says about why the signal was sent, see sigaction(2)
+pub fn describe_si_code(signal: Signal, si_code: i32) -> &'static str {
+    match (signal, si_code) {
+        (_, libc::SI_USER) => "sent by kill()",
+        (_, libc::SI_KERNEL) => "sent by the kernel",
+        (_, libc::SI_QUEUE) => "sent by sigqueue()",
+        (_, libc::SI_TKILL) => "sent by tkill() or raise()",
+
+        (Signal::SIGSEGV, 1) => "address not mapped to object",
+        (Signal::SIGSEGV, 2) => "invalid permissions for mapped object",
+        (Signal::SIGSEGV, 3) => "failed address bound checks",
+        (Signal::SIGSEGV, 4) => "access denied by memory protection keys",
+
+        (Signal::SIGBUS, 1) => "invalid address alignment",
+        (Signal::SIGBUS, 2) => "nonexistent physical address",
+        (Signal::SIGBUS, 3) => "object-specific hardware error",
+        (Signal::SIGBUS, 4) => "hardware memory error consumed on a machine check",
+        (Signal::SIGBUS, 5) => "hardware memory error detected but not consumed",
+
+        (Signal::SIGFPE, 1) => "integer divide by zero",
+        (Signal::SIGFPE, 2) => "integer overflow",
+        (Signal::SIGFPE, 3) => "floating-point divide by zero",
+        (Signal::SIGFPE, 4) => "floating-point overflow",
+        (Signal::SIGFPE, 5) => "floating-point underflow",
+        (Signal::SIGFPE, 6) => "floating-point inexact result",
+        (Signal::SIGFPE, 7) => "floating-point invalid operation",
+        (Signal::SIGFPE, 8) => "subscript out of range",
+
+        (Signal::SIGILL, 1) => "illegal opcode",
+        (Signal::SIGILL, 2) => "illegal operand",
+        (Signal::SIGILL, 3) => "illegal addressing mode",
+        (Signal::SIGILL, 4) => "illegal trap",
+        (Signal::SIGILL, 5) => "privileged opcode",
+        (Signal::SIGILL, 6) => "privileged register",
+        (Signal::SIGILL, 7) => "coprocessor error",
+        (Signal::SIGILL, 8) => "internal stack error",
+
+        _ => "unknown cause",
+    }
+}
@@ -1,14 +1,65 @@
 use gimli::{self, Dwarf};
 
-use object::{Object, ObjectSection};
+use object::{
+    Object, ObjectSection, ObjectSymbol, ObjectSymbolTable, RelocationTarget, SymbolKind,
+};
 use std::borrow;
 use std::cell::Ref;
+use std::collections::HashMap;
 use std::error::Error;
 
 // type TopLevelDwarfRef = Dwarf<EndianSlice<'dbg, RunTimeEndian>>;
 
+#[derive(Debug)]
+pub struct ElfSymbol {
+    pub name: String,
+    pub demangled: String,
+    pub addr: u64,
+    pub size: u64,
+    pub section: Option<String>,
+    pub is_func: bool,
+}
+
+/// A loaded section of an ELF file, with both its link-time address and
+/// where its contents live in the file (absent for .bss and the like).
+#[derive(Debug)]
+pub struct ElfSection {
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+    pub file_range: Option<(u64, u64)>,
+}
+
+/// A line table row: the code from start up to end belongs to a source line.
+#[derive(Debug)]
+pub struct LineRow {
+    pub start: u64,
+    pub end: u64,
+    pub file: String,
+    pub line: u64,
+}
+
+/// An inlined call site covering some address, as described by a
+/// DW_TAG_inlined_subroutine entry.
+#[derive(Debug)]
+pub struct InlinedCall {
+    pub name: String,
+    pub entry_pc: u64,
+    pub call_file: Option<String>,
+    pub call_line: u64,
+}
+
+/// The function containing some address, along with the chain of inlined
+/// calls nested inside of it there (outermost first).
+#[derive(Debug, Default)]
+pub struct FunctionScope {
+    pub function: Option<String>,
+    pub function_range: Option<(u64, u64)>,
+    pub inlined: Vec<InlinedCall>,
+}
+
 pub fn load_dwarf_data(f_buf: &[u8]) -> Result<Dwarf<borrow::Cow<'_, [u8]>>, Box<dyn Error>> {
-    let elf_obj = object::File::parse(&*f_buf)?;
+    let elf_obj = object::File::parse(f_buf)?;
 
     let section_loader = |section: gimli::SectionId| -> Result<borrow::Cow<[u8]>, gimli::Error> {
         match elf_obj.section_by_name(section.name()) {
@@ -24,43 +75,725 @@ pub fn load_dwarf_data(f_buf: &[u8]) -> Result<Dwarf<borrow::Cow<'_, [u8]>>, Box
     Ok(dwarf_cow)
 }
 
-pub fn src_line_to_addr(
+pub fn load_elf_symbols(f_buf: &[u8]) -> Result<Vec<ElfSymbol>, Box<dyn Error>> {
+    let elf_obj = object::File::parse(f_buf)?;
+
+    let mut symbols: Vec<ElfSymbol> = elf_obj
+        .symbols()
+        .chain(elf_obj.dynamic_symbols())
+        .filter(|sym| matches!(sym.kind(), SymbolKind::Text | SymbolKind::Data))
+        .filter(|sym| sym.is_definition() && sym.address() != 0)
+        .filter_map(|sym| {
+            let name = sym.name().ok()?;
+            let section = sym
+                .section_index()
+                .and_then(|idx| elf_obj.section_by_index(idx).ok())
+                .and_then(|section| section.name().ok().map(String::from));
+
+            Some(ElfSymbol {
+                name: name.to_string(),
+                demangled: demangle(name),
+                addr: sym.address(),
+                size: sym.size(),
+                section,
+                is_func: sym.kind() == SymbolKind::Text,
+            })
+        })
+        .collect();
+
+    symbols.extend(plt_symbols(&elf_obj));
+    symbols.sort_by_key(|sym| sym.addr);
+    symbols.dedup_by(|a, b| a.addr == b.addr && a.name == b.name);
+
+    Ok(symbols)
+}
+
+/// Names the PLT stubs that calls into shared libraries go through as
+/// "name@plt", by matching each stub's `jmp *slot(%rip)` with the dynamic
+/// relocation that fills in that GOT slot.
+fn plt_symbols(elf_obj: &object::File<'_>) -> Vec<ElfSymbol> {
+    let (Some(relocs), Some(dynsyms)) = (
+        elf_obj.dynamic_relocations(),
+        elf_obj.dynamic_symbol_table(),
+    ) else {
+        return Vec::new();
+    };
+
+    let slots: HashMap<u64, String> = relocs
+        .filter_map(|(slot, reloc)| match reloc.target() {
+            RelocationTarget::Symbol(idx) => {
+                let name = dynsyms.symbol_by_index(idx).ok()?.name().ok()?;
+                Some((slot, name.to_string())).filter(|_| !name.is_empty())
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut symbols = Vec::new();
+
+    for section in elf_obj.sections() {
+        let Ok(section_name @ (".plt" | ".plt.sec" | ".plt.got")) = section.name() else {
+            continue;
+        };
+        let Ok(code) = section.data() else {
+            continue;
+        };
+
+        for pos in 0..code.len().saturating_sub(5) {
+            if code[pos..pos + 2] != [0xFF, 0x25] {
+                continue;
+            }
+
+            let disp = i32::from_le_bytes(code[pos + 2..pos + 6].try_into().unwrap());
+            let next_addr = section.address() + pos as u64 + 6;
+            let Some(name) = slots.get(&next_addr.wrapping_add(disp as i64 as u64)) else {
+                continue;
+            };
+
+            // The stub starts at any endbr64 and bnd prefix ahead of the jmp
+            let mut start = pos;
+            if start >= 1 && code[start - 1] == 0xF2 {
+                start -= 1;
+            }
+            if start >= 4 && code[start - 4..start] == [0xF3, 0x0F, 0x1E, 0xFA] {
+                start -= 4;
+            }
+
+            symbols.push(ElfSymbol {
+                name: format!("{}@plt", name),
+                demangled: format!("{}@plt", demangle(name)),
+                addr: section.address() + start as u64,
+                size: (pos + 6 - start) as u64,
+                section: Some(section_name.to_string()),
+                is_func: true,
+            });
+        }
+    }
+
+    symbols
+}
+
+pub fn load_elf_sections(f_buf: &[u8]) -> Result<Vec<ElfSection>, Box<dyn Error>> {
+    let elf_obj = object::File::parse(f_buf)?;
+
+    Ok(elf_obj
+        .sections()
+        .filter(|section| section.address() != 0 && section.size() != 0)
+        .filter_map(|section| {
+            Some(ElfSection {
+                name: section.name().ok()?.to_string(),
+                addr: section.address(),
+                size: section.size(),
+                file_range: section.file_range(),
+            })
+        })
+        .collect())
+}
+
+/// Demangles Rust (legacy and v0) and Itanium C++ symbol names, handing
+/// back the original name for anything else (plain C symbols, mostly).
+pub fn demangle(name: &str) -> String {
+    if let Ok(sym) = rustc_demangle::try_demangle(name) {
+        // The alternate format leaves off the trailing ::h<hash>
+        return format!("{:#}", sym);
+    }
+
+    if name.starts_with("_Z") {
+        if let Ok(sym) = cpp_demangle::Symbol::new(name) {
+            if let Ok(demangled) = sym.demangle(&cpp_demangle::DemangleOptions::default()) {
+                return demangled;
+            }
+        }
+    }
+
+    name.to_string()
+}
+
+/// Finds the symbol covering a (file-relative) address, along with the
+/// offset of that address into it.
+pub fn addr_to_symbol(symbols: &[ElfSymbol], addr: u64) -> Option<(&ElfSymbol, u64)> {
+    symbols
+        .iter()
+        .rev()
+        .find(|sym| sym.addr <= addr && addr < sym.addr + sym.size.max(1))
+        .map(|sym| (sym, addr - sym.addr))
+}
+
+/// Resolves a function name to its symbols. The name may be the raw
+/// mangled name, a fully qualified demangled path, or any trailing
+/// portion of that path, so "parse", "parser::parse" and
+/// "mycrate::parser::parse" all match mycrate::parser::parse.
+pub fn symbol_name_to_addrs<'a>(symbols: &'a [ElfSymbol], query: &str) -> Vec<&'a ElfSymbol> {
+    symbols
+        .iter()
+        .filter(|sym| sym.is_func)
+        .filter(|sym| sym.name == query || name_matches(&sym.demangled, query))
+        .collect()
+}
+
+fn name_matches(demangled: &str, query: &str) -> bool {
+    // Ignore C++ parameter lists when matching on the path
+    let path = demangled.split('(').next().unwrap_or(demangled);
+
+    path == query
+        || path
+            .strip_suffix(query)
+            .is_some_and(|prefix| prefix.ends_with("::"))
+}
+
+/// Resolves a source line to every place code was generated for it: one
+/// location per function (or inlined instance) containing an is_stmt row
+/// for the line, with the prologue skipped when the line opens a function.
+/// Lines without any code (blank lines, comments) fall forward to the next
+/// line that has some, so the line actually used is returned alongside.
+pub fn src_line_to_addrs(
     dwarf_cow: Ref<'_, Dwarf<borrow::Cow<'_, [u8]>>>,
     filename: &str,
     line_num: u64,
+) -> Result<(u64, Vec<u64>), Box<dyn Error>> {
+    let dwarf =
+        dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, gimli::RunTimeEndian::Little));
+
+    let (mut candidates, next_line) = line_candidates(&dwarf, filename, line_num)?;
+    let mut used_line = line_num;
+
+    if candidates.is_empty() {
+        if let Some(next_line) = next_line {
+            candidates = line_candidates(&dwarf, filename, next_line)?.0;
+            used_line = next_line;
+        }
+    }
+
+    candidates.sort_unstable();
+    candidates.dedup();
+
+    // Keep the lowest address in each function or inlined instance
+    let mut seen_scopes = Vec::new();
+    let mut addrs = Vec::new();
+
+    for addr in candidates {
+        let scope = scope_at(&dwarf, addr)?;
+        let scope_key = (
+            scope.function_range.map(|(low, _)| low),
+            scope.inlined.last().map(|call| call.entry_pc),
+        );
+
+        if seen_scopes.contains(&scope_key) {
+            continue;
+        }
+
+        seen_scopes.push(scope_key);
+        addrs.push(skip_prologue_in(&dwarf, &scope, addr)?);
+    }
+
+    if addrs.is_empty() {
+        Err(Box::new(gimli::Error::InvalidAddressRange))
+    } else {
+        Ok((used_line, addrs))
+    }
+}
+
+/// Collects the address starting each run of is_stmt rows for a line, and
+/// notes the closest later line in the same file that has rows of its own.
+fn line_candidates<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    filename: &str,
+    line_num: u64,
+) -> gimli::Result<(Vec<u64>, Option<u64>)> {
+    let mut candidates = Vec::new();
+    let mut next_line: Option<u64> = None;
+
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+
+        let Some(line_prog) = unit.line_program.clone() else {
+            continue;
+        };
+
+        let mut rows = line_prog.rows();
+        let mut prev_matched = false;
+
+        while let Some((header, row)) = rows.next_row()? {
+            if row.end_sequence() {
+                prev_matched = false;
+                continue;
+            }
+
+            if !row.is_stmt() {
+                continue;
+            }
+
+            let row_line = row.line().map(u64::from).unwrap_or(0);
+
+            if row_line < line_num || next_line.is_some_and(|next| row_line > next) {
+                prev_matched = false;
+                continue;
+            }
+
+            if !file_path(dwarf, &unit, header, row.file_index())
+                .is_some_and(|path| file_matches(&path, filename))
+            {
+                prev_matched = false;
+                continue;
+            }
+
+            if row_line > line_num {
+                next_line = Some(row_line);
+                prev_matched = false;
+                continue;
+            }
+
+            // Only the first row of a run of rows for the line starts it
+            if !prev_matched {
+                candidates.push(row.address());
+            }
+
+            prev_matched = true;
+        }
+    }
+
+    Ok((candidates, next_line))
+}
+
+/// Moves a breakpoint at the entry of a function past its prologue, so
+/// that the frame is set up (and arguments are in place) when it is hit.
+/// Uses the prologue_end flag when present, otherwise the first row
+/// belonging to a later line. Any other address is handed back unchanged.
+pub fn skip_prologue(
+    dwarf_cow: Ref<'_, Dwarf<borrow::Cow<'_, [u8]>>>,
+    addr: u64,
 ) -> Result<u64, Box<dyn Error>> {
-    let dwarf = dwarf_cow
-        .borrow(|section| gimli::EndianSlice::new(&*section, gimli::RunTimeEndian::Little));
+    let dwarf =
+        dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, gimli::RunTimeEndian::Little));
+
+    let scope = scope_at(&dwarf, addr)?;
+
+    Ok(skip_prologue_in(&dwarf, &scope, addr)?)
+}
+
+/// Maps an address back to the source file and line of the line table row
+/// covering it.
+pub fn addr_to_src_line(
+    dwarf_cow: Ref<'_, Dwarf<borrow::Cow<'_, [u8]>>>,
+    addr: u64,
+) -> Result<(String, u64), Box<dyn Error>> {
+    let dwarf =
+        dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, gimli::RunTimeEndian::Little));
+
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+
+        if !unit_contains(&dwarf, &unit, addr)? {
+            continue;
+        }
+
+        let Some(line_prog) = unit.line_program.clone() else {
+            continue;
+        };
+
+        let mut rows = line_prog.rows();
+        let mut prev_row: Option<(u64, u64, u64)> = None;
 
+        while let Some((header, row)) = rows.next_row()? {
+            if let Some((prev_addr, file_idx, line)) = prev_row {
+                if prev_addr <= addr && addr < row.address() {
+                    let file = header
+                        .file(file_idx)
+                        .map(|file| dwarf.attr_string(&unit, file.path_name()))
+                        .transpose()?
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| String::from("??"));
+
+                    return Ok((file, line));
+                }
+            }
+
+            prev_row = if row.end_sequence() {
+                None
+            } else {
+                let line = row.line().map(u64::from).unwrap_or(0);
+                Some((row.address(), row.file_index(), line))
+            };
+        }
+    }
+
+    Err(Box::new(gimli::Error::InvalidAddressRange))
+}
+
+/// Every line table row overlapping an address range, with the files' full
+/// paths, so a run of instructions can be mapped to source in one pass.
+pub fn src_lines_in(
+    dwarf_cow: Ref<'_, Dwarf<borrow::Cow<'_, [u8]>>>,
+    start: u64,
+    end: u64,
+) -> Result<Vec<LineRow>, Box<dyn Error>> {
+    let dwarf =
+        dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, gimli::RunTimeEndian::Little));
+
+    let mut line_rows = Vec::new();
     let mut iter = dwarf.units();
     while let Some(header) = iter.next()? {
         let unit = dwarf.unit(header)?;
 
-        // Iterate over the Debugging Information Entries (DIEs) in the unit.
+        let Some(line_prog) = unit.line_program.clone() else {
+            continue;
+        };
+
+        let mut rows = line_prog.rows();
+        let mut prev_row: Option<(u64, u64, u64)> = None;
+
+        while let Some((header, row)) = rows.next_row()? {
+            if let Some((prev_addr, file_idx, line)) = prev_row {
+                if prev_addr < end && start < row.address() {
+                    line_rows.push(LineRow {
+                        start: prev_addr,
+                        end: row.address(),
+                        file: file_path(&dwarf, &unit, header, file_idx)
+                            .unwrap_or_else(|| String::from("??")),
+                        line,
+                    });
+                }
+            }
+
+            prev_row = if row.end_sequence() {
+                None
+            } else {
+                let line = row.line().map(u64::from).unwrap_or(0);
+                Some((row.address(), row.file_index(), line))
+            };
+        }
+    }
+
+    Ok(line_rows)
+}
+
+/// Finds the function and any inlined calls that are live at an address.
+pub fn function_scope_at(
+    dwarf_cow: Ref<'_, Dwarf<borrow::Cow<'_, [u8]>>>,
+    addr: u64,
+) -> Result<FunctionScope, Box<dyn Error>> {
+    let dwarf =
+        dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, gimli::RunTimeEndian::Little));
+
+    Ok(scope_at(&dwarf, addr)?)
+}
+
+/// Entry addresses of every place a function matching the query was
+/// inlined into.
+pub fn inlined_instances_of(
+    dwarf_cow: Ref<'_, Dwarf<borrow::Cow<'_, [u8]>>>,
+    query: &str,
+) -> Result<Vec<(String, u64)>, Box<dyn Error>> {
+    let dwarf =
+        dwarf_cow.borrow(|section| gimli::EndianSlice::new(section, gimli::RunTimeEndian::Little));
+
+    let mut instances = Vec::new();
+
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
         let mut entries = unit.entries();
 
-        if let Some((_depth, top_die)) = entries.next_dfs()? {
-            if let (Ok(Some(_comp_dir_atval)), Ok(Some(name_atval))) = (
-                top_die.attr_value(gimli::DwAt(0x1b)),
-                top_die.attr_value(gimli::DwAt(0x03)),
-            ) {
-                let cu_name = dwarf.attr_string(&unit, name_atval)?.to_string()?;
-
-                if cu_name == filename {
-                    let line_prog = unit.line_program.unwrap();
-                    let mut rows = line_prog.rows();
-
-                    while let Ok(Some((_header, row))) = rows.next_row() {
-                        if let Some(l) = row.line().map(u64::from) {
-                            if l == line_num {
-                                return Ok(row.address());
-                            }
-                        }
+        while let Some((_depth, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_inlined_subroutine {
+                continue;
+            }
+
+            if let Some(name) = die_name(&dwarf, &unit, entry, 0) {
+                if name_matches(&name, query) {
+                    instances.push((name, die_entry_pc(&dwarf, &unit, entry)?));
+                }
+            }
+        }
+    }
+
+    Ok(instances)
+}
+
+fn scope_at<R: gimli::Reader>(dwarf: &Dwarf<R>, addr: u64) -> gimli::Result<FunctionScope> {
+    let mut scope = FunctionScope::default();
+
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+
+        if !unit_contains(dwarf, &unit, addr)? {
+            continue;
+        }
+
+        let mut tree = unit.entries_tree(None)?;
+        collect_scope(dwarf, &unit, tree.root()?, addr, &mut scope)?;
+
+        if scope.function_range.is_some() {
+            break;
+        }
+    }
+
+    Ok(scope)
+}
+
+fn skip_prologue_in<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    scope: &FunctionScope,
+    addr: u64,
+) -> gimli::Result<u64> {
+    let Some((low, high)) = scope.function_range else {
+        return Ok(addr);
+    };
+
+    if addr != low {
+        return Ok(addr);
+    }
+
+    let mut iter = dwarf.units();
+    while let Some(header) = iter.next()? {
+        let unit = dwarf.unit(header)?;
+
+        if !unit_contains(dwarf, &unit, addr)? {
+            continue;
+        }
+
+        let Some(line_prog) = unit.line_program.clone() else {
+            continue;
+        };
+
+        let mut rows = line_prog.rows();
+        let mut entry_line = None;
+        let mut next_line_addr = None;
+
+        while let Some((_header, row)) = rows.next_row()? {
+            if row.end_sequence() || row.address() < low || row.address() >= high {
+                continue;
+            }
+
+            if row.prologue_end() {
+                return Ok(row.address());
+            }
+
+            match entry_line {
+                None => entry_line = row.line(),
+                Some(line) => {
+                    if next_line_addr.is_none()
+                        && row.is_stmt()
+                        && row.address() > low
+                        && row.line() != Some(line)
+                    {
+                        next_line_addr = Some(row.address());
                     }
                 }
             }
         }
+
+        return Ok(next_line_addr.unwrap_or(addr));
     }
 
-    Err(Box::new(gimli::Error::InvalidAddressRange))
+    Ok(addr)
+}
+
+fn file_path<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    header: &gimli::LineProgramHeader<R>,
+    file_idx: u64,
+) -> Option<String> {
+    let attr_to_string = |attr| -> Option<String> {
+        let name = dwarf.attr_string(unit, attr).ok()?;
+        name.to_string_lossy().ok().map(|s| s.into_owned())
+    };
+
+    let file = header.file(file_idx)?;
+    let name = attr_to_string(file.path_name())?;
+
+    if name.starts_with('/') {
+        return Some(name);
+    }
+
+    match file.directory(header).and_then(attr_to_string) {
+        Some(dir) => Some(format!("{}/{}", dir, name)),
+        None => Some(name),
+    }
+}
+
+/// A user supplied file name matches a path if it is the path itself or
+/// some trailing set of its components ("main.c", "src/main.c")
+fn file_matches(path: &str, filename: &str) -> bool {
+    path == filename
+        || path
+            .strip_suffix(filename)
+            .is_some_and(|prefix| prefix.ends_with('/'))
+}
+
+fn collect_scope<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    node: gimli::EntriesTreeNode<R>,
+    addr: u64,
+    scope: &mut FunctionScope,
+) -> gimli::Result<()> {
+    let mut children = node.children();
+
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+
+        match entry.tag() {
+            gimli::DW_TAG_subprogram
+            | gimli::DW_TAG_inlined_subroutine
+            | gimli::DW_TAG_lexical_block => {
+                if !die_contains(dwarf, unit, entry, addr)? {
+                    continue;
+                }
+
+                if entry.tag() == gimli::DW_TAG_subprogram {
+                    scope.function = die_name(dwarf, unit, entry, 0);
+                    scope.function_range = die_bounds(dwarf, unit, entry)?;
+                } else if entry.tag() == gimli::DW_TAG_inlined_subroutine {
+                    let call_file = match entry.attr_value(gimli::DW_AT_call_file)? {
+                        Some(gimli::AttributeValue::FileIndex(idx)) => Some(idx),
+                        Some(other) => other.udata_value(),
+                        None => None,
+                    }
+                    .and_then(|idx| unit.line_program.as_ref()?.header().file(idx))
+                    .and_then(|file| dwarf.attr_string(unit, file.path_name()).ok())
+                    .and_then(|name| name.to_string_lossy().ok().map(|s| s.into_owned()));
+
+                    scope.inlined.push(InlinedCall {
+                        name: die_name(dwarf, unit, entry, 0).unwrap_or_else(|| "??".into()),
+                        entry_pc: die_entry_pc(dwarf, unit, entry)?,
+                        call_file,
+                        call_line: entry
+                            .attr_value(gimli::DW_AT_call_line)?
+                            .and_then(|line| line.udata_value())
+                            .unwrap_or(0),
+                    });
+                }
+
+                // Scopes don't overlap, so nothing else at this level can hold addr
+                return collect_scope(dwarf, unit, child, addr, scope);
+            }
+
+            // Functions in C++ and Rust nest inside of these
+            gimli::DW_TAG_namespace
+            | gimli::DW_TAG_structure_type
+            | gimli::DW_TAG_class_type
+            | gimli::DW_TAG_union_type => {
+                collect_scope(dwarf, unit, child, addr, scope)?;
+
+                if scope.function.is_some() {
+                    return Ok(());
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn unit_contains<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    addr: u64,
+) -> gimli::Result<bool> {
+    let mut ranges = dwarf.unit_ranges(unit)?;
+
+    while let Some(range) = ranges.next()? {
+        if range.begin <= addr && addr < range.end {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn die_contains<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    entry: &gimli::DebuggingInformationEntry<R>,
+    addr: u64,
+) -> gimli::Result<bool> {
+    let mut ranges = dwarf.die_ranges(unit, entry)?;
+
+    while let Some(range) = ranges.next()? {
+        if range.begin <= addr && addr < range.end {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn die_bounds<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    entry: &gimli::DebuggingInformationEntry<R>,
+) -> gimli::Result<Option<(u64, u64)>> {
+    let mut bounds: Option<(u64, u64)> = None;
+    let mut ranges = dwarf.die_ranges(unit, entry)?;
+
+    while let Some(range) = ranges.next()? {
+        bounds = Some(match bounds {
+            Some((low, high)) => (low.min(range.begin), high.max(range.end)),
+            None => (range.begin, range.end),
+        });
+    }
+
+    Ok(bounds)
+}
+
+fn die_entry_pc<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    entry: &gimli::DebuggingInformationEntry<R>,
+) -> gimli::Result<u64> {
+    for attr in [gimli::DW_AT_entry_pc, gimli::DW_AT_low_pc] {
+        if let Some(val) = entry.attr_value(attr)? {
+            if let Some(addr) = dwarf.attr_address(unit, val)? {
+                return Ok(addr);
+            }
+        }
+    }
+
+    let mut lowest = None;
+    let mut ranges = dwarf.die_ranges(unit, entry)?;
+    while let Some(range) = ranges.next()? {
+        lowest = Some(lowest.map_or(range.begin, |low: u64| low.min(range.begin)));
+    }
+
+    lowest.ok_or(gimli::Error::InvalidAddressRange)
+}
+
+/// Names a subprogram or inlined subroutine, preferring the demangled
+/// linkage name and chasing abstract origins/specifications to find one.
+fn die_name<R: gimli::Reader>(
+    dwarf: &Dwarf<R>,
+    unit: &gimli::Unit<R>,
+    entry: &gimli::DebuggingInformationEntry<R>,
+    depth: usize,
+) -> Option<String> {
+    let attr_to_string = |attr| -> Option<String> {
+        let val = entry.attr_value(attr).ok()??;
+        let name = dwarf.attr_string(unit, val).ok()?;
+        name.to_string_lossy().ok().map(|s| s.into_owned())
+    };
+
+    if let Some(linkage_name) = attr_to_string(gimli::DW_AT_linkage_name)
+        .or_else(|| attr_to_string(gimli::DW_AT_MIPS_linkage_name))
+    {
+        return Some(demangle(&linkage_name));
+    }
+
+    // Origins may chain (inlined instance -> abstract instance -> declaration)
+    if depth < 4 {
+        for attr in [gimli::DW_AT_abstract_origin, gimli::DW_AT_specification] {
+            if let Ok(Some(gimli::AttributeValue::UnitRef(offset))) = entry.attr_value(attr) {
+                let origin = unit.entry(offset).ok()?;
+                return die_name(dwarf, unit, &origin, depth + 1);
+            }
+        }
+    }
+
+    attr_to_string(gimli::DW_AT_name)
 }
@@ -0,0 +1,498 @@
+use crate::traceedb::command::read_c_string;
+use crate::traceedb::dbg::TraceeDbg;
+use nix::{errno::Errno, unistd::Pid};
+
+/// Longest string or buffer shown in a syscall's arguments, as in strace
+const MAX_ARG_STRING: usize = 32;
+
+/// x86_64 syscalls by number. Each has a letter per argument saying how it's
+/// shown: d decimal, x hex, o octal, s a C string (mostly paths) and b a
+/// buffer whose length is the argument after it.
+const SYSCALLS: &[(u64, &str, &str)] = &[
+    (0, "read", "dxd"),
+    (1, "write", "dbd"),
+    (2, "open", "sxo"),
+    (3, "close", "d"),
+    (4, "stat", "sx"),
+    (5, "fstat", "dx"),
+    (6, "lstat", "sx"),
+    (7, "poll", "xdd"),
+    (8, "lseek", "ddd"),
+    (9, "mmap", "xdxxdd"),
+    (10, "mprotect", "xdx"),
+    (11, "munmap", "xd"),
+    (12, "brk", "x"),
+    (13, "rt_sigaction", "dxxd"),
+    (14, "rt_sigprocmask", "dxxd"),
+    (15, "rt_sigreturn", ""),
+    (16, "ioctl", "dxx"),
+    (17, "pread64", "dxdd"),
+    (18, "pwrite64", "dbdd"),
+    (19, "readv", "dxd"),
+    (20, "writev", "dxd"),
+    (21, "access", "sd"),
+    (22, "pipe", "x"),
+    (23, "select", "dxxxx"),
+    (24, "sched_yield", ""),
+    (25, "mremap", "xddxx"),
+    (26, "msync", "xdx"),
+    (27, "mincore", "xdx"),
+    (28, "madvise", "xdd"),
+    (29, "shmget", "ddx"),
+    (30, "shmat", "dxx"),
+    (31, "shmctl", "ddx"),
+    (32, "dup", "d"),
+    (33, "dup2", "dd"),
+    (34, "pause", ""),
+    (35, "nanosleep", "xx"),
+    (36, "getitimer", "dx"),
+    (37, "alarm", "d"),
+    (38, "setitimer", "dxx"),
+    (39, "getpid", ""),
+    (40, "sendfile", "ddxd"),
+    (41, "socket", "ddd"),
+    (42, "connect", "dxd"),
+    (43, "accept", "dxx"),
+    (44, "sendto", "dbdxxd"),
+    (45, "recvfrom", "dxdxxx"),
+    (46, "sendmsg", "dxx"),
+    (47, "recvmsg", "dxx"),
+    (48, "shutdown", "dd"),
+    (49, "bind", "dxd"),
+    (50, "listen", "dd"),
+    (51, "getsockname", "dxx"),
+    (52, "getpeername", "dxx"),
+    (53, "socketpair", "dddx"),
+    (54, "setsockopt", "dddxd"),
+    (55, "getsockopt", "dddxx"),
+    (56, "clone", "xxxxx"),
+    (57, "fork", ""),
+    (58, "vfork", ""),
+    (59, "execve", "sxx"),
+    (60, "exit", "d"),
+    (61, "wait4", "dxxx"),
+    (62, "kill", "dd"),
+    (63, "uname", "x"),
+    (64, "semget", "ddx"),
+    (65, "semop", "dxd"),
+    (66, "semctl", "dddx"),
+    (67, "shmdt", "x"),
+    (68, "msgget", "dx"),
+    (69, "msgsnd", "dxdx"),
+    (70, "msgrcv", "dxddx"),
+    (71, "msgctl", "ddx"),
+    (72, "fcntl", "ddx"),
+    (73, "flock", "dd"),
+    (74, "fsync", "d"),
+    (75, "fdatasync", "d"),
+    (76, "truncate", "sd"),
+    (77, "ftruncate", "dd"),
+    (78, "getdents", "dxd"),
+    (79, "getcwd", "xd"),
+    (80, "chdir", "s"),
+    (81, "fchdir", "d"),
+    (82, "rename", "ss"),
+    (83, "mkdir", "so"),
+    (84, "rmdir", "s"),
+    (85, "creat", "so"),
+    (86, "link", "ss"),
+    (87, "unlink", "s"),
+    (88, "symlink", "ss"),
+    (89, "readlink", "sxd"),
+    (90, "chmod", "so"),
+    (91, "fchmod", "do"),
+    (92, "chown", "sdd"),
+    (93, "fchown", "ddd"),
+    (94, "lchown", "sdd"),
+    (95, "umask", "o"),
+    (96, "gettimeofday", "xx"),
+    (97, "getrlimit", "dx"),
+    (98, "getrusage", "dx"),
+    (99, "sysinfo", "x"),
+    (100, "times", "x"),
+    (101, "ptrace", "ddxx"),
+    (102, "getuid", ""),
+    (103, "syslog", "dxd"),
+    (104, "getgid", ""),
+    (105, "setuid", "d"),
+    (106, "setgid", "d"),
+    (107, "geteuid", ""),
+    (108, "getegid", ""),
+    (109, "setpgid", "dd"),
+    (110, "getppid", ""),
+    (111, "getpgrp", ""),
+    (112, "setsid", ""),
+    (113, "setreuid", "dd"),
+    (114, "setregid", "dd"),
+    (115, "getgroups", "dx"),
+    (116, "setgroups", "dx"),
+    (117, "setresuid", "ddd"),
+    (118, "getresuid", "xxx"),
+    (119, "setresgid", "ddd"),
+    (120, "getresgid", "xxx"),
+    (121, "getpgid", "d"),
+    (122, "setfsuid", "d"),
+    (123, "setfsgid", "d"),
+    (124, "getsid", "d"),
+    (125, "capget", "xx"),
+    (126, "capset", "xx"),
+    (127, "rt_sigpending", "xd"),
+    (128, "rt_sigtimedwait", "xxxd"),
+    (129, "rt_sigqueueinfo", "ddx"),
+    (130, "rt_sigsuspend", "xd"),
+    (131, "sigaltstack", "xx"),
+    (132, "utime", "sx"),
+    (133, "mknod", "sod"),
+    (134, "uselib", "s"),
+    (135, "personality", "x"),
+    (136, "ustat", "dx"),
+    (137, "statfs", "sx"),
+    (138, "fstatfs", "dx"),
+    (139, "sysfs", "dxx"),
+    (140, "getpriority", "dd"),
+    (141, "setpriority", "ddd"),
+    (142, "sched_setparam", "dx"),
+    (143, "sched_getparam", "dx"),
+    (144, "sched_setscheduler", "ddx"),
+    (145, "sched_getscheduler", "d"),
+    (146, "sched_get_priority_max", "d"),
+    (147, "sched_get_priority_min", "d"),
+    (148, "sched_rr_get_interval", "dx"),
+    (149, "mlock", "xd"),
+    (150, "munlock", "xd"),
+    (151, "mlockall", "x"),
+    (152, "munlockall", ""),
+    (153, "vhangup", ""),
+    (154, "modify_ldt", "dxd"),
+    (155, "pivot_root", "ss"),
+    (156, "_sysctl", "x"),
+    (157, "prctl", "dxxxx"),
+    (158, "arch_prctl", "dx"),
+    (159, "adjtimex", "x"),
+    (160, "setrlimit", "dx"),
+    (161, "chroot", "s"),
+    (162, "sync", ""),
+    (163, "acct", "s"),
+    (164, "settimeofday", "xx"),
+    (165, "mount", "sssxx"),
+    (166, "umount2", "sx"),
+    (167, "swapon", "sx"),
+    (168, "swapoff", "s"),
+    (169, "reboot", "xxdx"),
+    (170, "sethostname", "sd"),
+    (171, "setdomainname", "sd"),
+    (172, "iopl", "d"),
+    (173, "ioperm", "ddd"),
+    (174, "create_module", "sd"),
+    (175, "init_module", "xds"),
+    (176, "delete_module", "sx"),
+    (177, "get_kernel_syms", "x"),
+    (178, "query_module", "sdxdx"),
+    (179, "quotactl", "dsdx"),
+    (180, "nfsservctl", "dxx"),
+    (181, "getpmsg", ""),
+    (182, "putpmsg", ""),
+    (183, "afs_syscall", ""),
+    (184, "tuxcall", ""),
+    (185, "security", ""),
+    (186, "gettid", ""),
+    (187, "readahead", "ddd"),
+    (188, "setxattr", "ssxdx"),
+    (189, "lsetxattr", "ssxdx"),
+    (190, "fsetxattr", "dsxdx"),
+    (191, "getxattr", "ssxd"),
+    (192, "lgetxattr", "ssxd"),
+    (193, "fgetxattr", "dsxd"),
+    (194, "listxattr", "sxd"),
+    (195, "llistxattr", "sxd"),
+    (196, "flistxattr", "dxd"),
+    (197, "removexattr", "ss"),
+    (198, "lremovexattr", "ss"),
+    (199, "fremovexattr", "ds"),
+    (200, "tkill", "dd"),
+    (201, "time", "x"),
+    (202, "futex", "xddxxd"),
+    (203, "sched_setaffinity", "ddx"),
+    (204, "sched_getaffinity", "ddx"),
+    (205, "set_thread_area", "x"),
+    (206, "io_setup", "dx"),
+    (207, "io_destroy", "x"),
+    (208, "io_getevents", "xddxx"),
+    (209, "io_submit", "xdx"),
+    (210, "io_cancel", "xxx"),
+    (211, "get_thread_area", "x"),
+    (212, "lookup_dcookie", "dxd"),
+    (213, "epoll_create", "d"),
+    (214, "epoll_ctl_old", ""),
+    (215, "epoll_wait_old", ""),
+    (216, "remap_file_pages", "xdddx"),
+    (217, "getdents64", "dxd"),
+    (218, "set_tid_address", "x"),
+    (219, "restart_syscall", ""),
+    (220, "semtimedop", "dxdx"),
+    (221, "fadvise64", "dddd"),
+    (222, "timer_create", "dxx"),
+    (223, "timer_settime", "ddxx"),
+    (224, "timer_gettime", "dx"),
+    (225, "timer_getoverrun", "d"),
+    (226, "timer_delete", "d"),
+    (227, "clock_settime", "dx"),
+    (228, "clock_gettime", "dx"),
+    (229, "clock_getres", "dx"),
+    (230, "clock_nanosleep", "ddxx"),
+    (231, "exit_group", "d"),
+    (232, "epoll_wait", "dxdd"),
+    (233, "epoll_ctl", "dddx"),
+    (234, "tgkill", "ddd"),
+    (235, "utimes", "sx"),
+    (236, "vserver", ""),
+    (237, "mbind", "xddxdx"),
+    (238, "set_mempolicy", "dxd"),
+    (239, "get_mempolicy", "xxdxx"),
+    (240, "mq_open", "sdox"),
+    (241, "mq_unlink", "s"),
+    (242, "mq_timedsend", "dbddx"),
+    (243, "mq_timedreceive", "dxdxx"),
+    (244, "mq_notify", "dx"),
+    (245, "mq_getsetattr", "dxx"),
+    (246, "kexec_load", "xdxx"),
+    (247, "waitid", "ddxdx"),
+    (248, "add_key", "ssxdd"),
+    (249, "request_key", "sssd"),
+    (250, "keyctl", "dxxxx"),
+    (251, "ioprio_set", "ddd"),
+    (252, "ioprio_get", "dd"),
+    (253, "inotify_init", ""),
+    (254, "inotify_add_watch", "dsx"),
+    (255, "inotify_rm_watch", "dd"),
+    (256, "migrate_pages", "ddxx"),
+    (257, "openat", "dsxo"),
+    (258, "mkdirat", "dso"),
+    (259, "mknodat", "dsod"),
+    (260, "fchownat", "dsddx"),
+    (261, "futimesat", "dsx"),
+    (262, "newfstatat", "dsxx"),
+    (263, "unlinkat", "dsx"),
+    (264, "renameat", "dsds"),
+    (265, "linkat", "dsdsx"),
+    (266, "symlinkat", "sds"),
+    (267, "readlinkat", "dsxd"),
+    (268, "fchmodat", "dso"),
+    (269, "faccessat", "dsd"),
+    (270, "pselect6", "dxxxxx"),
+    (271, "ppoll", "xdxxd"),
+    (272, "unshare", "x"),
+    (273, "set_robust_list", "xd"),
+    (274, "get_robust_list", "dxx"),
+    (275, "splice", "dxdxdx"),
+    (276, "tee", "dddx"),
+    (277, "sync_file_range", "dddx"),
+    (278, "vmsplice", "dxdx"),
+    (279, "move_pages", "ddxxxx"),
+    (280, "utimensat", "dsxx"),
+    (281, "epoll_pwait", "dxddxd"),
+    (282, "signalfd", "dxd"),
+    (283, "timerfd_create", "dx"),
+    (284, "eventfd", "d"),
+    (285, "fallocate", "dddd"),
+    (286, "timerfd_settime", "dxxx"),
+    (287, "timerfd_gettime", "dx"),
+    (288, "accept4", "dxxx"),
+    (289, "signalfd4", "dxdx"),
+    (290, "eventfd2", "dx"),
+    (291, "epoll_create1", "x"),
+    (292, "dup3", "ddx"),
+    (293, "pipe2", "xx"),
+    (294, "inotify_init1", "x"),
+    (295, "preadv", "dxdd"),
+    (296, "pwritev", "dxdd"),
+    (297, "rt_tgsigqueueinfo", "dddx"),
+    (298, "perf_event_open", "xdddx"),
+    (299, "recvmmsg", "dxdxx"),
+    (300, "fanotify_init", "xx"),
+    (301, "fanotify_mark", "dxxds"),
+    (302, "prlimit64", "ddxx"),
+    (303, "name_to_handle_at", "dsxxx"),
+    (304, "open_by_handle_at", "dxx"),
+    (305, "clock_adjtime", "dx"),
+    (306, "syncfs", "d"),
+    (307, "sendmmsg", "dxdx"),
+    (308, "setns", "dx"),
+    (309, "getcpu", "xxx"),
+    (310, "process_vm_readv", "dxdxdx"),
+    (311, "process_vm_writev", "dxdxdx"),
+    (312, "kcmp", "ddddd"),
+    (313, "finit_module", "dsx"),
+    (314, "sched_setattr", "dxx"),
+    (315, "sched_getattr", "dxdx"),
+    (316, "renameat2", "dsdsx"),
+    (317, "seccomp", "dxx"),
+    (318, "getrandom", "xdx"),
+    (319, "memfd_create", "sx"),
+    (320, "kexec_file_load", "dddsx"),
+    (321, "bpf", "dxd"),
+    (322, "execveat", "dsxxx"),
+    (323, "userfaultfd", "x"),
+    (324, "membarrier", "dxd"),
+    (325, "mlock2", "xdx"),
+    (326, "copy_file_range", "dxdxdx"),
+    (327, "preadv2", "dxddx"),
+    (328, "pwritev2", "dxddx"),
+    (329, "pkey_mprotect", "xdxd"),
+    (330, "pkey_alloc", "xx"),
+    (331, "pkey_free", "d"),
+    (332, "statx", "dsxxx"),
+    (333, "io_pgetevents", "xddxxx"),
+    (334, "rseq", "xdxx"),
+    (424, "pidfd_send_signal", "ddxx"),
+    (425, "io_uring_setup", "dx"),
+    (426, "io_uring_enter", "dddxxd"),
+    (427, "io_uring_register", "ddxd"),
+    (428, "open_tree", "dsx"),
+    (429, "move_mount", "dsdsx"),
+    (430, "fsopen", "sx"),
+    (431, "fsconfig", "ddsxd"),
+    (432, "fsmount", "dxx"),
+    (433, "fspick", "dsx"),
+    (434, "pidfd_open", "dx"),
+    (435, "clone3", "xd"),
+    (436, "close_range", "ddx"),
+    (437, "openat2", "dsxd"),
+    (438, "pidfd_getfd", "ddx"),
+    (439, "faccessat2", "dsdx"),
+    (440, "process_madvise", "dxddx"),
+    (441, "epoll_pwait2", "dxdxxd"),
+    (442, "mount_setattr", "dsxxd"),
+    (443, "quotactl_fd", "ddxx"),
+    (444, "landlock_create_ruleset", "xdx"),
+    (445, "landlock_add_rule", "ddxx"),
+    (446, "landlock_restrict_self", "dx"),
+    (447, "memfd_secret", "x"),
+    (448, "process_mrelease", "dx"),
+    (449, "futex_waitv", "xdxxd"),
+    (450, "set_mempolicy_home_node", "xddx"),
+    (451, "cachestat", "dxxx"),
+    (452, "fchmodat2", "dsox"),
+    (453, "map_shadow_stack", "xdx"),
+    (454, "futex_wake", "xxdx"),
+    (455, "futex_wait", "xxxxxd"),
+    (456, "futex_requeue", "xxdd"),
+    (457, "statmount", "xxdx"),
+    (458, "listmount", "xxdx"),
+    (459, "lsm_get_self_attr", "dxxx"),
+    (460, "lsm_set_self_attr", "dxdx"),
+    (461, "lsm_list_modules", "xxx"),
+    (462, "mseal", "xdx"),
+];
+
+fn lookup(nr: u64) -> Option<&'static (u64, &'static str, &'static str)> {
+    SYSCALLS.iter().find(|(number, ..)| *number == nr)
+}
+
+pub fn syscall_name(nr: u64) -> Option<&'static str> {
+    lookup(nr).map(|(_, name, _)| *name)
+}
+
+/// Accepts a syscall's name or its number
+pub fn parse_syscall(name: &str) -> Option<u64> {
+    if let Ok(nr) = name.parse() {
+        return lookup(nr).map(|(nr, ..)| *nr);
+    }
+
+    SYSCALLS
+        .iter()
+        .find(|(_, syscall, _)| *syscall == name)
+        .map(|(nr, ..)| *nr)
+}
+
+/// Syscalls that don't come back when they succeed, so there's no return
+/// value to wait for
+pub fn never_returns(nr: u64) -> bool {
+    matches!(syscall_name(nr), Some("exit" | "exit_group"))
+}
+
+/// Renders a syscall as it was made, `write(1, "hi\n", 3)`, with strings and
+/// buffers read out of the tracee
+pub fn format_syscall(pid: Pid, dbg: &TraceeDbg, nr: u64, args: &[u64; 6]) -> String {
+    let Some((_, name, kinds)) = lookup(nr) else {
+        let args: Vec<String> = args.iter().map(|arg| format!("{:#x}", arg)).collect();
+        return format!("syscall_{}({})", nr, args.join(", "));
+    };
+
+    let args: Vec<String> = kinds
+        .chars()
+        .enumerate()
+        .map(|(idx, kind)| format_arg(pid, dbg, kind, args[idx], args.get(idx + 1)))
+        .collect();
+
+    format!("{}({})", name, args.join(", "))
+}
+
+fn format_arg(pid: Pid, dbg: &TraceeDbg, kind: char, arg: u64, next: Option<&u64>) -> String {
+    match kind {
+        // Ints are only sign extended to 32 bits, as with AT_FDCWD
+        'd' if arg >> 32 == 0 => (arg as u32 as i32).to_string(),
+        'd' => (arg as i64).to_string(),
+        'o' if arg == 0 => String::from("0"),
+        'o' => format!("0{:o}", arg),
+        _ if arg == 0 => String::from("NULL"),
+
+        's' => match read_c_string(pid, dbg, arg) {
+            Ok(bytes) => quote(&bytes, false),
+            Err(_) => format!("{:#x}", arg),
+        },
+
+        'b' => {
+            let len = next.copied().unwrap_or(0) as usize;
+
+            match dbg.read_memory(pid, arg, len.min(MAX_ARG_STRING)) {
+                Ok(bytes) => quote(&bytes, len > MAX_ARG_STRING),
+                Err(_) => format!("{:#x}", arg),
+            }
+        }
+
+        _ => format!("{:#x}", arg),
+    }
+}
+
+/// Quotes and escapes a string, cut short with "..." after the quote like
+/// strace does
+fn quote(bytes: &[u8], truncated: bool) -> String {
+    let shown = &bytes[..bytes.len().min(MAX_ARG_STRING)];
+    let ellipsis = if truncated || bytes.len() > MAX_ARG_STRING {
+        "..."
+    } else {
+        ""
+    };
+
+    format!("\"{}\"{}", shown.escape_ascii(), ellipsis)
+}
+
+/// Renders a syscall's return value, naming and describing the errno when
+/// it failed
+pub fn format_return(nr: u64, ret: i64) -> String {
+    if (-4095..0).contains(&ret) {
+        let errno = -ret as i32;
+
+        // The kernel's own errnos for an interrupted syscall, which the
+        // tracee never sees
+        let restart = match errno {
+            512 => Some(("ERESTARTSYS", "To be restarted if SA_RESTART is set")),
+            513 => Some(("ERESTARTNOINTR", "To be restarted")),
+            514 => Some(("ERESTARTNOHAND", "To be restarted if no handler")),
+            516 => Some(("ERESTART_RESTARTBLOCK", "Interrupted by signal")),
+            _ => None,
+        };
+
+        return match (restart, Errno::from_i32(errno)) {
+            (Some((name, desc)), _) => format!("? {} ({})", name, desc),
+            (None, Errno::UnknownErrno) => format!("-1 errno {}", errno),
+            (None, errno) => format!("-1 {:?} ({})", errno, errno.desc()),
+        };
+    }
+
+    match syscall_name(nr) {
+        Some("mmap" | "mremap" | "brk" | "shmat") => format!("{:#x}", ret),
+        _ => ret.to_string(),
+    }
+}
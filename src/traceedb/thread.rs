@@ -0,0 +1,57 @@
+use nix::{sys::signal::Signal, unistd::Pid};
+
+use std::fs;
+
+/// How a thread was last left, which decides how it's picked back up after
+/// a stop the debugger deals with on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    Stopped,
+    Running,
+    Stepping,
+}
+
+#[derive(Debug)]
+pub struct TraceeThread {
+    /// Numbered from 1 in order of appearance, as the user refers to them
+    pub id: usize,
+    /// The process it belongs to, i.e. the tid of its main thread
+    pub pid: Pid,
+    pub tid: Pid,
+    pub state: ThreadState,
+    /// A SIGSTOP we sent to halt it that it hasn't stopped for yet
+    pub stop_requested: bool,
+    /// The signal to deliver when it next resumes, if any
+    pub pending_signal: Option<Signal>,
+    /// The syscall it's between the entry and exit stops of, as its number
+    /// and arguments
+    pub syscall: Option<(u64, [u64; 6])>,
+    /// The breakpoint it stopped on, which it's stepped past with the trap
+    /// lifted when it resumes
+    pub trap_hit: Option<u64>,
+}
+
+impl TraceeThread {
+    pub fn new(id: usize, pid: Pid, tid: Pid, state: ThreadState) -> Self {
+        Self {
+            id,
+            pid,
+            tid,
+            state,
+            stop_requested: false,
+            pending_signal: None,
+            syscall: None,
+            trap_hit: None,
+        }
+    }
+}
+
+/// Looks up which process a thread belongs to
+pub fn thread_group_of(tid: Pid) -> Option<Pid> {
+    fs::read_to_string(format!("/proc/{}/status", tid))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("Tgid:"))
+        .and_then(|tgid| tgid.trim().parse().ok())
+        .map(Pid::from_raw)
+}
@@ -0,0 +1,136 @@
+use gimli::{
+    BaseAddresses, CfaRule, EhFrame, RegisterRule, RunTimeEndian, UnwindContext, UnwindSection,
+    X86_64,
+};
+use nix::{sys::ptrace, unistd::Pid};
+use object::{Object, ObjectSection};
+
+use std::borrow;
+use std::error::Error;
+use std::ffi::c_void;
+
+const MAX_FRAMES: usize = 64;
+
+/// Call frame information pulled from .eh_frame, used to walk the stack of a
+/// stopped tracee
+#[derive(Debug)]
+pub struct UnwindInfo<'a> {
+    eh_frame: borrow::Cow<'a, [u8]>,
+    eh_frame_addr: u64,
+    text_addr: u64,
+}
+
+pub fn load_unwind_info(f_buf: &[u8]) -> Result<UnwindInfo<'_>, Box<dyn Error>> {
+    let elf_obj = object::File::parse(f_buf)?;
+
+    let eh_frame = elf_obj
+        .section_by_name(".eh_frame")
+        .ok_or("No .eh_frame section in program")?;
+
+    Ok(UnwindInfo {
+        eh_frame: eh_frame.uncompressed_data()?,
+        eh_frame_addr: eh_frame.address(),
+        text_addr: elf_obj
+            .section_by_name(".text")
+            .map(|text| text.address())
+            .unwrap_or(0),
+    })
+}
+
+impl<'a> UnwindInfo<'a> {
+    /// Copies the section out of the buffer it was loaded from
+    pub fn into_owned(self) -> UnwindInfo<'static> {
+        UnwindInfo {
+            eh_frame: borrow::Cow::Owned(self.eh_frame.into_owned()),
+            eh_frame_addr: self.eh_frame_addr,
+            text_addr: self.text_addr,
+        }
+    }
+
+    /// Walks the stack of a stopped tracee, returning the pc of each frame
+    /// innermost first. Frames outside of the program's own .eh_frame (libc,
+    /// mostly) are stepped over by following the frame pointer chain instead.
+    pub fn backtrace(&self, pid: Pid, load_bias: u64) -> Result<Vec<u64>, &'static str> {
+        let regs = ptrace::getregs(pid).map_err(|_| "failed to PTRACE_GETREGS")?;
+
+        let eh_frame = EhFrame::new(&self.eh_frame, RunTimeEndian::Little);
+        let bases = BaseAddresses::default()
+            .set_eh_frame(self.eh_frame_addr)
+            .set_text(self.text_addr);
+        let mut ctx = UnwindContext::new();
+
+        let (mut pc, mut rsp, mut rbp) = (regs.rip, regs.rsp, regs.rbp);
+        let mut frames = Vec::new();
+
+        while pc != 0 && frames.len() < MAX_FRAMES {
+            // Return addresses point past the call, so look up the call itself
+            let lookup_pc = if frames.is_empty() { pc } else { pc - 1 };
+
+            let row = eh_frame.unwind_info_for_address(
+                &bases,
+                &mut ctx,
+                lookup_pc.wrapping_sub(load_bias),
+                EhFrame::cie_from_offset,
+            );
+
+            let caller = match row {
+                Ok(row) => {
+                    let cfa = match row.cfa() {
+                        CfaRule::RegisterAndOffset {
+                            register: X86_64::RSP,
+                            offset,
+                        } => Some(rsp.wrapping_add(*offset as u64)),
+                        CfaRule::RegisterAndOffset {
+                            register: X86_64::RBP,
+                            offset,
+                        } => Some(rbp.wrapping_add(*offset as u64)),
+                        _ => None,
+                    };
+
+                    match (cfa, row.register(X86_64::RA)) {
+                        (Some(cfa), RegisterRule::Offset(ra_offset)) => {
+                            let caller_rbp = match row.register(X86_64::RBP) {
+                                RegisterRule::Offset(offset) => {
+                                    read_u64(pid, cfa.wrapping_add(offset as u64)).unwrap_or(rbp)
+                                }
+                                _ => rbp,
+                            };
+
+                            read_u64(pid, cfa.wrapping_add(ra_offset as u64))
+                                .map(|ret_addr| (cfa, ret_addr, caller_rbp))
+                        }
+
+                        // Undefined return address marks the outermost frame
+                        _ => None,
+                    }
+                }
+
+                Err(_) => read_u64(pid, rbp.wrapping_add(8))
+                    .zip(read_u64(pid, rbp))
+                    .map(|(ret_addr, caller_rbp)| (rbp.wrapping_add(16), ret_addr, caller_rbp)),
+            };
+
+            let Some((cfa, ret_addr, caller_rbp)) = caller else {
+                frames.push(pc);
+                break;
+            };
+
+            frames.push(pc);
+
+            // Bail out rather than loop forever on a corrupt stack
+            if cfa <= rsp {
+                break;
+            }
+
+            (pc, rsp, rbp) = (ret_addr, cfa, caller_rbp);
+        }
+
+        Ok(frames)
+    }
+}
+
+fn read_u64(pid: Pid, addr: u64) -> Option<u64> {
+    ptrace::read(pid, addr as *mut c_void)
+        .map(|word| word as u64)
+        .ok()
+}